@@ -0,0 +1,309 @@
+use crate::local_search::cmp_partial;
+use crate::local_search::common::{clamp_vector4, length_range_inclusive};
+use crate::local_search::problem::{BoxDomain, Domain, Problem};
+use nalgebra::{Matrix4, Vector4};
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use std::time::Instant;
+
+const DIMENSIONS: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// Population size of the first run, `4 + 3 ln n` when `None`.
+    pub initial_population_size: Option<usize>,
+    /// Factor by which the population grows on every restart (IPOP).
+    pub population_growth: f64,
+    /// Initial step size as a fraction of the domain width.
+    pub initial_step_size: f64,
+    /// Stop as soon as a value at most `target` is found.
+    pub target: Option<f64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_population_size: None,
+            population_growth: 2.0,
+            initial_step_size: 0.3,
+            target: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub argument: Vector4<f64>,
+    pub value: f64,
+    pub evaluations: u64,
+    pub evaluations_to_target: Option<u64>,
+    pub restarts: u32,
+}
+
+impl Solution {
+    fn reached_target(&self, target: Option<f64>) -> bool {
+        matches!(target, Some(target) if self.value <= target)
+    }
+}
+
+struct Parameters {
+    population_size: usize,
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_c: f64,
+    c_sigma: f64,
+    c_1: f64,
+    c_mu: f64,
+    damping: f64,
+    expected_norm: f64,
+}
+
+impl Parameters {
+    fn new(population_size: usize) -> Self {
+        let n = DIMENSIONS;
+        let mu = population_size / 2;
+        let weights: Vec<_> = (1..=mu)
+            .map(|i| f64::ln(mu as f64 + 0.5) - f64::ln(i as f64))
+            .collect();
+        let sum: f64 = weights.iter().sum();
+        let weights: Vec<_> = weights.into_iter().map(|w| w / sum).collect();
+        let mu_eff = weights.iter().map(|w| w * w).sum::<f64>().recip();
+
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        let c_mu = f64::min(
+            1.0 - c_1,
+            2.0 * (mu_eff - 2.0 + mu_eff.recip()) / ((n + 2.0).powi(2) + mu_eff),
+        );
+        let damping =
+            1.0 + 2.0 * f64::max(0.0, f64::sqrt((mu_eff - 1.0) / (n + 1.0)) - 1.0) + c_sigma;
+        let expected_norm = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+
+        Self {
+            population_size,
+            weights,
+            mu_eff,
+            c_c,
+            c_sigma,
+            c_1,
+            c_mu,
+            damping,
+            expected_norm,
+        }
+    }
+}
+
+struct State {
+    mean: Vector4<f64>,
+    step_size: f64,
+    covariance: Matrix4<f64>,
+    path_c: Vector4<f64>,
+    path_sigma: Vector4<f64>,
+    generation: u32,
+}
+
+impl State {
+    fn new(mean: Vector4<f64>, step_size: f64) -> Self {
+        Self {
+            mean,
+            step_size,
+            covariance: Matrix4::identity(),
+            path_c: Vector4::zeros(),
+            path_sigma: Vector4::zeros(),
+            generation: 0,
+        }
+    }
+}
+
+/// Runs one CMA-ES until it stagnates, returns `false` if the time limit or the target was hit.
+fn run<P>(
+    parameters: &Parameters,
+    state: &mut State,
+    best: &mut Solution,
+    config: &Config,
+    time_limit: Instant,
+) -> bool
+where
+    P: Problem,
+    P::Domain: BoxDomain,
+{
+    let bounds = <P::Domain as BoxDomain>::BOUNDS;
+    let rng = &mut thread_rng();
+    let initial_step_size = state.step_size;
+    let stagnation_limit = 10 + (30.0 * DIMENSIONS / parameters.population_size as f64) as usize;
+    let mut history = Vec::new();
+
+    loop {
+        if Instant::now() >= time_limit {
+            return false;
+        }
+
+        let eigen = state.covariance.symmetric_eigen();
+        if eigen
+            .eigenvalues
+            .iter()
+            .any(|&e| !e.is_finite() || e <= 0.0)
+        {
+            return true;
+        }
+        let deviations = eigen.eigenvalues.map(f64::sqrt);
+        let basis = eigen.eigenvectors;
+        let transform = basis * Matrix4::from_diagonal(&deviations);
+        let inverse_sqrt =
+            basis * Matrix4::from_diagonal(&deviations.map(f64::recip)) * basis.transpose();
+
+        let mut offspring: Vec<_> = std::iter::repeat_with(|| {
+            let z: Vector4<f64> = Vector4::from_distribution(&StandardNormal, rng);
+            let x = clamp_vector4(&bounds, &(state.mean + transform * z * state.step_size));
+            let value = P::value(&x);
+            (x, value)
+        })
+        .take(parameters.population_size)
+        .collect();
+        offspring.sort_by(|(_, a), (_, b)| cmp_partial(a, b));
+
+        best.evaluations += parameters.population_size as u64;
+        let (argument, value) = offspring[0];
+        if value < best.value {
+            best.argument = argument;
+            best.value = value;
+        }
+        if best.evaluations_to_target.is_none() && best.reached_target(config.target) {
+            best.evaluations_to_target = Some(best.evaluations);
+            return false;
+        }
+
+        let old_mean = state.mean;
+        state.mean = offspring
+            .iter()
+            .zip(&parameters.weights)
+            .fold(Vector4::zeros(), |sum, ((x, _), w)| sum + x * *w);
+        let mean_step = (state.mean - old_mean) / state.step_size;
+
+        let c_sigma = parameters.c_sigma;
+        state.path_sigma = state.path_sigma * (1.0 - c_sigma)
+            + inverse_sqrt * mean_step * f64::sqrt(c_sigma * (2.0 - c_sigma) * parameters.mu_eff);
+        state.generation += 1;
+        let decay = 1.0 - (1.0 - c_sigma).powi(2 * state.generation as i32);
+        let h_sigma = state.path_sigma.norm() / decay.sqrt() / parameters.expected_norm
+            < 1.4 + 2.0 / (DIMENSIONS + 1.0);
+        let h_sigma = if h_sigma { 1.0 } else { 0.0 };
+
+        let c_c = parameters.c_c;
+        state.path_c = state.path_c * (1.0 - c_c)
+            + mean_step * (h_sigma * f64::sqrt(c_c * (2.0 - c_c) * parameters.mu_eff));
+
+        let rank_mu =
+            offspring
+                .iter()
+                .zip(&parameters.weights)
+                .fold(Matrix4::zeros(), |sum, ((x, _), w)| {
+                    let y = (x - old_mean) / state.step_size;
+                    sum + y * y.transpose() * *w
+                });
+        let rank_one = state.path_c * state.path_c.transpose()
+            + state.covariance * ((1.0 - h_sigma) * c_c * (2.0 - c_c));
+        let covariance = state.covariance * (1.0 - parameters.c_1 - parameters.c_mu)
+            + rank_one * parameters.c_1
+            + rank_mu * parameters.c_mu;
+        state.covariance = (covariance + covariance.transpose()) * 0.5;
+
+        state.step_size *= f64::exp(
+            (c_sigma / parameters.damping)
+                * (state.path_sigma.norm() / parameters.expected_norm - 1.0),
+        );
+
+        history.push(offspring[0].1);
+        let max_deviation = deviations.max() * state.step_size;
+        let condition = eigen.eigenvalues.max() / eigen.eigenvalues.min();
+        let stagnated = history.len() > stagnation_limit && {
+            let recent = &history[history.len() - stagnation_limit..];
+            let (min, max) = recent
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                    (min.min(v), max.max(v))
+                });
+            max - min < 1e-12
+        };
+
+        if !state.step_size.is_finite()
+            || max_deviation < 1e-12 * initial_step_size
+            || condition > 1e14
+            || stagnated
+        {
+            return true;
+        }
+    }
+}
+
+pub fn search<P>(config: &Config, time_limit: Instant) -> Solution
+where
+    P: Problem,
+    P::Domain: BoxDomain,
+{
+    let bounds = <P::Domain as BoxDomain>::BOUNDS;
+    let step_size = config.initial_step_size * length_range_inclusive(&bounds);
+    let mut population_size = config
+        .initial_population_size
+        .unwrap_or_else(|| 4 + (3.0 * DIMENSIONS.ln()) as usize)
+        .max(2);
+
+    let starting_point = P::Domain::random(1.0);
+    let mut best = Solution {
+        value: P::value(&starting_point),
+        argument: starting_point,
+        evaluations: 1,
+        evaluations_to_target: None,
+        restarts: 0,
+    };
+    if best.reached_target(config.target) {
+        best.evaluations_to_target = Some(1);
+        return best;
+    }
+
+    let mut mean = starting_point;
+    loop {
+        let parameters = Parameters::new(population_size);
+        let mut state = State::new(mean, step_size);
+
+        if !run::<P>(&parameters, &mut state, &mut best, config, time_limit) {
+            return best;
+        }
+
+        best.restarts += 1;
+        population_size = (population_size as f64 * config.population_growth) as usize;
+        mean = P::Domain::random(1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_search::griewank::Griewank;
+    use crate::local_search::sphere::Sphere;
+    use std::time::Duration;
+
+    #[test]
+    fn reaches_target_on_sphere() {
+        let config = Config {
+            target: Some(1e-10),
+            ..Config::default()
+        };
+        let solution = search::<Sphere>(&config, Instant::now() + Duration::from_secs(1));
+
+        assert!(solution.value <= 1e-10);
+        assert_eq!(solution.evaluations_to_target, Some(solution.evaluations));
+    }
+
+    #[test]
+    fn reaches_target_on_griewank() {
+        let config = Config {
+            target: Some(1e-8),
+            ..Config::default()
+        };
+        let solution = search::<Griewank>(&config, Instant::now() + Duration::from_secs(1));
+
+        assert!(solution.evaluations_to_target.is_some(), "{:?}", solution);
+    }
+}
@@ -1 +1,4 @@
+pub mod cma_es;
+pub mod differential_evolution;
 pub mod local_search;
+pub mod particle_swarm;
@@ -3,15 +3,19 @@ use problem::{Domain, Problem};
 use std::cmp::Ordering;
 use std::time::Instant;
 
-mod common;
+pub(crate) mod common;
 pub mod griewank;
 pub mod happy_cat;
 pub mod problem;
+pub mod salomon;
+pub mod sphere;
+pub mod tabu;
 
 type Value<P> = assoc_fcs!(P: Problem->Domain->Value);
 type Argument<P> = assoc_fcs!(P: Problem->Domain->Argument);
+type Candidate<P> = (Argument<P>, Value<P>);
 
-fn cmp_partial<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+pub(crate) fn cmp_partial<T: PartialOrd>(a: &T, b: &T) -> Ordering {
     if let Some(ordering) = a.partial_cmp(b) {
         ordering
     } else {
@@ -19,36 +23,231 @@ fn cmp_partial<T: PartialOrd>(a: &T, b: &T) -> Ordering {
     }
 }
 
-pub fn search<P: Problem>(time_limit: Instant) -> (Argument<P>, Value<P>) {
+const NEIGHBOURHOOD_SIZE: usize = 20;
+const INITIAL_RADIUS: f64 = 0.5;
+const MINIMAL_RADIUS: f64 = 1e-12;
+const ADAPTATION_FACTOR: f64 = 0.85;
+
+/// Neighbourhood radius adapted with the 1/5th success rule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct StepSize {
+    radius: f64,
+}
+
+impl StepSize {
+    fn new() -> Self {
+        Self::with_radius(INITIAL_RADIUS)
+    }
+
+    fn with_radius(radius: f64) -> Self {
+        Self { radius }
+    }
+
+    fn adapt(&mut self, successes: usize, trials: usize) {
+        if 5 * successes > trials {
+            self.radius = f64::min(self.radius / ADAPTATION_FACTOR, INITIAL_RADIUS);
+        } else {
+            self.radius *= ADAPTATION_FACTOR;
+        }
+    }
+
+    fn collapsed(&self) -> bool {
+        self.radius < MINIMAL_RADIUS
+    }
+}
+
+fn neighbours<P: Problem>(
+    current: &Argument<P>,
+    radius: f64,
+) -> impl Iterator<Item = Candidate<P>> + '_ {
+    std::iter::repeat_with(move || {
+        let next = P::Domain::random_near(current, radius);
+        let next_value = P::value(&next);
+        (next, next_value)
+    })
+    .take(NEIGHBOURHOOD_SIZE)
+}
+
+/// Which of the sampled neighbours improving on the current point the search moves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The best of all sampled neighbours.
+    BestImprovement,
+    /// The first improving neighbour, the rest of the neighbourhood is not sampled.
+    FirstImprovement,
+    /// The worst of the improving neighbours, for diversification.
+    WorstImproving,
+}
+
+struct Selected<T> {
+    choice: Option<T>,
+    successes: usize,
+    trials: usize,
+}
+
+impl Selection {
+    fn select<A, V: PartialOrd>(
+        self,
+        current_value: &V,
+        candidates: impl Iterator<Item = (A, V)>,
+    ) -> Selected<(A, V)> {
+        let mut trials = 0;
+        let mut better = candidates
+            .inspect(|_| trials += 1)
+            .filter(|(_, value)| value < current_value);
+
+        let (choice, successes) = match self {
+            Selection::FirstImprovement => {
+                let choice = better.next();
+                let successes = if choice.is_some() { 1 } else { 0 };
+                (choice, successes)
+            }
+            Selection::BestImprovement | Selection::WorstImproving => {
+                let better: Vec<_> = better.collect();
+                let successes = better.len();
+                let by_value = |(_, a): &(A, V), (_, b): &(A, V)| cmp_partial(a, b);
+                let choice = if self == Selection::BestImprovement {
+                    better.into_iter().min_by(by_value)
+                } else {
+                    better.into_iter().max_by(by_value)
+                };
+                (choice, successes)
+            }
+        };
+
+        Selected {
+            choice,
+            successes,
+            trials,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub selection: Selection,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            selection: Selection::BestImprovement,
+        }
+    }
+}
+
+fn descend<P: Problem>(config: &Config, time_limit: Instant) -> Candidate<P> {
     let starting_point = P::Domain::random(1.0);
-    let mut best = (starting_point.clone(), P::value(&starting_point));
-    let mut current = starting_point;
-
-    fn find_next<P: Problem>(
-        current: &Argument<P>,
-        best_value: &Value<P>,
-    ) -> Option<(Argument<P>, Value<P>)> {
-        let neighbours = std::iter::repeat_with(|| {
-            let next = P::Domain::random_near(current, 0.5);
-            let next_value = P::value(&next);
-            (next, next_value)
-        });
-        let better = neighbours
-            .take(5000)
-            .filter(|(_, next_value)| next_value < best_value)
-            .take(20);
-
-        better.max_by(|(_, a), (_, b)| cmp_partial(a, b))
+    let mut current = (starting_point.clone(), P::value(&starting_point));
+    let mut step_size = StepSize::new();
+
+    while Instant::now() < time_limit && !step_size.collapsed() {
+        let selected = config
+            .selection
+            .select(&current.1, neighbours::<P>(&current.0, step_size.radius));
+        step_size.adapt(selected.successes, selected.trials);
+
+        if let Some(next) = selected.choice {
+            current = next;
+        }
     }
 
+    current
+}
+
+pub fn search<P: Problem>(config: &Config, time_limit: Instant) -> (Argument<P>, Value<P>) {
+    let mut best = descend::<P>(config, time_limit);
+
     while Instant::now() < time_limit {
-        if let Some((next, next_value)) = find_next::<P>(&current, &best.1) {
-            best = (next.clone(), next_value);
-            current = next;
-        } else {
-            break;
+        let restarted = descend::<P>(config, time_limit);
+        if restarted.1 < best.1 {
+            best = restarted;
         }
     }
 
     best
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_search::sphere::Sphere;
+    use std::time::Duration;
+
+    #[test]
+    fn step_size_follows_one_fifth_rule() {
+        let mut step_size = StepSize::new();
+        step_size.adapt(0, NEIGHBOURHOOD_SIZE);
+        assert!(step_size.radius < INITIAL_RADIUS);
+
+        let shrunk = step_size.radius;
+        step_size.adapt(NEIGHBOURHOOD_SIZE / 5, NEIGHBOURHOOD_SIZE);
+        assert!(step_size.radius < shrunk);
+
+        let shrunk = step_size.radius;
+        step_size.adapt(NEIGHBOURHOOD_SIZE / 5 + 1, NEIGHBOURHOOD_SIZE);
+        assert!(step_size.radius > shrunk);
+    }
+
+    fn candidates(values: &[f64]) -> impl Iterator<Item = (usize, f64)> + '_ {
+        values.iter().copied().enumerate()
+    }
+
+    #[test]
+    fn best_improvement_picks_lowest() {
+        let selected =
+            Selection::BestImprovement.select(&10.0, candidates(&[12.0, 8.0, 3.0, 9.0, 15.0]));
+
+        assert_eq!(selected.choice, Some((2, 3.0)));
+        assert_eq!(selected.successes, 3);
+        assert_eq!(selected.trials, 5);
+    }
+
+    #[test]
+    fn first_improvement_stops_sampling() {
+        let selected =
+            Selection::FirstImprovement.select(&10.0, candidates(&[12.0, 8.0, 3.0, 9.0, 15.0]));
+
+        assert_eq!(selected.choice, Some((1, 8.0)));
+        assert_eq!(selected.successes, 1);
+        assert_eq!(selected.trials, 2);
+    }
+
+    #[test]
+    fn worst_improving_picks_highest_improving() {
+        let selected =
+            Selection::WorstImproving.select(&10.0, candidates(&[12.0, 8.0, 3.0, 9.0, 15.0]));
+
+        assert_eq!(selected.choice, Some((3, 9.0)));
+        assert_eq!(selected.successes, 3);
+        assert_eq!(selected.trials, 5);
+    }
+
+    #[test]
+    fn no_improvement_selects_nothing() {
+        for &selection in &[
+            Selection::BestImprovement,
+            Selection::FirstImprovement,
+            Selection::WorstImproving,
+        ] {
+            let selected = selection.select(&1.0, candidates(&[1.0, 2.0, 3.0]));
+
+            assert_eq!(selected.choice, None);
+            assert_eq!(selected.successes, 0);
+            assert_eq!(selected.trials, 3);
+        }
+    }
+
+    #[test]
+    fn converges_on_sphere() {
+        for &selection in &[
+            Selection::BestImprovement,
+            Selection::FirstImprovement,
+            Selection::WorstImproving,
+        ] {
+            let config = Config { selection };
+            let (_, value) = search::<Sphere>(&config, Instant::now() + Duration::from_millis(100));
+            assert!(value < 1e-12, "{:?}: {}", selection, value);
+        }
+    }
+}
@@ -49,3 +49,20 @@ pub(crate) fn random_vector4_near(
         rng.sample(dist)
     })
 }
+
+#[inline(always)]
+pub(crate) fn distance_vector4(
+    domain_bound: RangeInclusive<f64>,
+    a: &Vector4<f64>,
+    b: &Vector4<f64>,
+) -> f64 {
+    (a - b).amax() / length_range_inclusive(&domain_bound)
+}
+
+#[inline(always)]
+pub(crate) fn clamp_vector4(
+    domain_bound: &RangeInclusive<f64>,
+    point: &Vector4<f64>,
+) -> Vector4<f64> {
+    point.map(|elem| elem.clamp(*domain_bound.start(), *domain_bound.end()))
+}
@@ -1,5 +1,5 @@
-use crate::local_search::common::{random_vector4, random_vector4_near};
-use crate::local_search::problem::{Domain, Problem, ProblemArgument, ProblemValue};
+use crate::local_search::common::{distance_vector4, random_vector4, random_vector4_near};
+use crate::local_search::problem::{BoxDomain, Domain, Problem, ProblemArgument, ProblemValue};
 use nalgebra::Vector4;
 use std::ops::RangeInclusive;
 
@@ -22,6 +22,14 @@ impl Domain for HappyCatDomain {
     fn random_near(point: &Self::Argument, scale: f64) -> Self::Argument {
         random_vector4_near(DOMAIN_BOUNDS, point, scale)
     }
+
+    fn distance(a: &Self::Argument, b: &Self::Argument) -> f64 {
+        distance_vector4(DOMAIN_BOUNDS, a, b)
+    }
+}
+
+impl BoxDomain for HappyCatDomain {
+    const BOUNDS: RangeInclusive<Scalar> = DOMAIN_BOUNDS;
 }
 
 pub const NUM_DIMENSIONS: u8 = 4;
@@ -51,6 +59,6 @@ mod tests {
 
     #[test]
     fn root() {
-        assert!(HappyCat::value(&Vector4::new(-1.0, -1.0, -1.0, -1.0)).abs() < std::f64::EPSILON);
+        assert!(HappyCat::value(&Vector4::new(-1.0, -1.0, -1.0, -1.0)).abs() < f64::EPSILON);
     }
 }
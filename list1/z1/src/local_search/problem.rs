@@ -1,13 +1,20 @@
 #![allow(type_alias_bounds)]
 
 use crate::assoc_fcs;
+use nalgebra::Vector4;
+use std::ops::RangeInclusive;
 
 pub trait Domain {
     type Argument: Clone;
-    type Value: PartialOrd;
+    type Value: PartialOrd + Clone;
 
     fn random(scale: f64) -> Self::Argument;
     fn random_near(point: &Self::Argument, scale: f64) -> Self::Argument;
+    fn distance(a: &Self::Argument, b: &Self::Argument) -> f64;
+}
+
+pub trait BoxDomain: Domain<Argument = Vector4<f64>, Value = f64> {
+    const BOUNDS: RangeInclusive<f64>;
 }
 
 pub type ProblemArgument<P: Problem> = assoc_fcs!(P: Problem->Domain->Argument);
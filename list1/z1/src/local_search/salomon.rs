@@ -0,0 +1,53 @@
+use crate::local_search::common::{distance_vector4, random_vector4, random_vector4_near};
+use crate::local_search::problem::{BoxDomain, Domain, Problem, ProblemArgument, ProblemValue};
+use nalgebra::Vector4;
+use std::f64 as scalar;
+use std::ops::RangeInclusive;
+
+pub type Scalar = f64;
+
+pub struct SalomonDomain;
+const DOMAIN_BOUNDS: RangeInclusive<Scalar> = -100.0..=100.0;
+
+impl Domain for SalomonDomain {
+    type Argument = Vector4<Scalar>;
+    type Value = Scalar;
+
+    fn random(scale: f64) -> Self::Argument {
+        random_vector4(DOMAIN_BOUNDS, scale)
+    }
+
+    fn random_near(point: &Self::Argument, scale: f64) -> Self::Argument {
+        random_vector4_near(DOMAIN_BOUNDS, point, scale)
+    }
+
+    fn distance(a: &Self::Argument, b: &Self::Argument) -> f64 {
+        distance_vector4(DOMAIN_BOUNDS, a, b)
+    }
+}
+
+impl BoxDomain for SalomonDomain {
+    const BOUNDS: RangeInclusive<Scalar> = DOMAIN_BOUNDS;
+}
+
+pub struct Salomon;
+
+impl Problem for Salomon {
+    type Domain = SalomonDomain;
+
+    fn value(argument: &ProblemArgument<Self>) -> ProblemValue<Self> {
+        let norm = argument.norm();
+
+        1.0 - Scalar::cos(2.0 * scalar::consts::PI * norm) + 0.1 * norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root() {
+        assert!(Salomon::value(&Vector4::new(0.0, 0.0, 0.0, 0.0)).abs() < f64::EPSILON);
+    }
+}
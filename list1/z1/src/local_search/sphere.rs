@@ -0,0 +1,50 @@
+use crate::local_search::common::{distance_vector4, random_vector4, random_vector4_near};
+use crate::local_search::problem::{BoxDomain, Domain, Problem, ProblemArgument, ProblemValue};
+use nalgebra::Vector4;
+use std::ops::RangeInclusive;
+
+pub type Scalar = f64;
+
+pub struct SphereDomain;
+const DOMAIN_BOUNDS: RangeInclusive<Scalar> = -5.12..=5.12;
+
+impl Domain for SphereDomain {
+    type Argument = Vector4<Scalar>;
+    type Value = Scalar;
+
+    fn random(scale: f64) -> Self::Argument {
+        random_vector4(DOMAIN_BOUNDS, scale)
+    }
+
+    fn random_near(point: &Self::Argument, scale: f64) -> Self::Argument {
+        random_vector4_near(DOMAIN_BOUNDS, point, scale)
+    }
+
+    fn distance(a: &Self::Argument, b: &Self::Argument) -> f64 {
+        distance_vector4(DOMAIN_BOUNDS, a, b)
+    }
+}
+
+impl BoxDomain for SphereDomain {
+    const BOUNDS: RangeInclusive<Scalar> = DOMAIN_BOUNDS;
+}
+
+pub struct Sphere;
+
+impl Problem for Sphere {
+    type Domain = SphereDomain;
+
+    fn value(argument: &ProblemArgument<Self>) -> ProblemValue<Self> {
+        argument.norm_squared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root() {
+        assert!(Sphere::value(&Vector4::new(0.0, 0.0, 0.0, 0.0)).abs() < f64::EPSILON);
+    }
+}
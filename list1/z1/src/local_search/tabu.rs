@@ -0,0 +1,140 @@
+use super::{cmp_partial, neighbours, Candidate, StepSize, NEIGHBOURHOOD_SIZE};
+use crate::local_search::problem::{Domain, Problem};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// Radius of the ball around a recently visited point which the search may not re-enter,
+    /// relative to the neighbourhood radius the point was left with.
+    pub tabu_radius: f64,
+    /// Number of most recently visited points that stay tabu.
+    pub tenure: usize,
+    /// Neighbourhood radius the search continues with once it collapses in a local optimum.
+    pub escape_radius: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tabu_radius: 0.5,
+            tenure: 50,
+            escape_radius: 0.02,
+        }
+    }
+}
+
+struct TabuList<D: Domain> {
+    balls: VecDeque<(D::Argument, f64)>,
+    tenure: usize,
+}
+
+impl<D: Domain> TabuList<D> {
+    fn new(tenure: usize) -> Self {
+        Self {
+            balls: VecDeque::with_capacity(tenure),
+            tenure,
+        }
+    }
+
+    fn push(&mut self, point: D::Argument, radius: f64) {
+        if self.tenure == 0 {
+            return;
+        }
+        if self.balls.len() == self.tenure {
+            self.balls.pop_front();
+        }
+        self.balls.push_back((point, radius));
+    }
+
+    fn contains(&self, point: &D::Argument) -> bool {
+        self.balls
+            .iter()
+            .any(|(center, radius)| D::distance(center, point) < *radius)
+    }
+}
+
+pub fn search<P: Problem>(config: &Config, time_limit: Instant) -> Candidate<P> {
+    let starting_point = P::Domain::random(1.0);
+    let mut current = (starting_point.clone(), P::value(&starting_point));
+    let mut best = current.clone();
+    let mut tabu = TabuList::<P::Domain>::new(config.tenure);
+    let mut step_size = StepSize::new();
+
+    while Instant::now() < time_limit {
+        let admissible: Vec<_> = neighbours::<P>(&current.0, step_size.radius)
+            .filter(|(argument, value)| *value < best.1 || !tabu.contains(argument))
+            .collect();
+        let successes = admissible
+            .iter()
+            .filter(|(_, value)| *value < current.1)
+            .count();
+        step_size.adapt(successes, NEIGHBOURHOOD_SIZE);
+
+        let next = admissible
+            .into_iter()
+            .min_by(|(_, a), (_, b)| cmp_partial(a, b));
+
+        if let Some(next) = next {
+            let (previous, _) = std::mem::replace(&mut current, next);
+            tabu.push(previous, config.tabu_radius * step_size.radius);
+
+            if current.1 < best.1 {
+                best = current.clone();
+            }
+        }
+
+        if step_size.collapsed() {
+            step_size = StepSize::with_radius(config.escape_radius);
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_search::griewank::Griewank;
+    use crate::local_search::sphere::{Sphere, SphereDomain};
+    use nalgebra::Vector4;
+    use std::time::Duration;
+
+    #[test]
+    fn tabu_list_covers_ball() {
+        let mut tabu = TabuList::<SphereDomain>::new(1);
+        tabu.push(Vector4::zeros(), 0.1);
+
+        assert!(tabu.contains(&Vector4::new(0.5, -0.5, 0.0, 1.0)));
+        assert!(!tabu.contains(&Vector4::new(0.0, 0.0, 0.0, 1.1)));
+    }
+
+    #[test]
+    fn tabu_list_forgets_after_tenure() {
+        let mut tabu = TabuList::<SphereDomain>::new(2);
+        tabu.push(Vector4::repeat(-5.0), 0.1);
+        tabu.push(Vector4::zeros(), 0.1);
+        tabu.push(Vector4::repeat(5.0), 0.1);
+
+        assert!(!tabu.contains(&Vector4::repeat(-5.0)));
+        assert!(tabu.contains(&Vector4::zeros()));
+        assert!(tabu.contains(&Vector4::repeat(5.0)));
+    }
+
+    #[test]
+    fn converges_on_sphere() {
+        let time_limit = Instant::now() + Duration::from_millis(100);
+        let (_, value) = search::<Sphere>(&Config::default(), time_limit);
+        assert!(value < 1e-12, "{}", value);
+    }
+
+    #[test]
+    fn converges_on_griewank() {
+        // the local minima around the origin are only a few hundredths apart, so this only
+        // checks that the search gets from values around 100 into the central basins
+        let time_limit = Instant::now() + Duration::from_millis(200);
+        let (argument, value) = search::<Griewank>(&Config::default(), time_limit);
+        assert!(value < 1.0, "{}", value);
+        assert_eq!(value, Griewank::value(&argument));
+    }
+}
@@ -1,23 +1,53 @@
 use ::z1::{
-    local_search,
+    cma_es, differential_evolution, local_search,
     local_search::griewank::Griewank,
     local_search::happy_cat::HappyCat,
-    local_search::problem::{Problem, ProblemArgument, ProblemValue},
+    local_search::problem::{BoxDomain, Problem, ProblemArgument, ProblemValue},
+    local_search::salomon::Salomon,
+    particle_swarm,
 };
 use std::io::Read;
 use std::ops::Add;
 use std::time::{Duration, Instant};
 
+/// Evaluations used in total and until the target was reached, counted by CMA-ES only.
+type Evaluations = Option<(u64, Option<u64>)>;
+
 #[allow(type_alias_bounds)]
-type SearchOk<P: Problem> = (ProblemArgument<P>, ProblemValue<P>, Duration);
+type SearchOk<P: Problem> = (ProblemArgument<P>, ProblemValue<P>, Duration, Evaluations);
 
-fn do_search<P: Problem>(duration_limit: Duration) -> SearchOk<P> {
+fn do_search<P>(
+    algorithm: u64,
+    duration_limit: Duration,
+    target: Option<f64>,
+) -> Result<SearchOk<P>, String>
+where
+    P: Problem,
+    P::Domain: BoxDomain,
+{
     let start = Instant::now();
-    let (arg, val) = local_search::search::<P>(Instant::now().add(duration_limit));
+    let time_limit = Instant::now().add(duration_limit);
+    let mut evaluations = None;
+    let (arg, val) = match algorithm {
+        0 => local_search::search::<P>(&Default::default(), time_limit),
+        1 => differential_evolution::search::<P>(&Default::default(), time_limit),
+        2 => particle_swarm::search::<P>(&Default::default(), time_limit),
+        3 => {
+            let config = cma_es::Config {
+                target,
+                ..Default::default()
+            };
+            let solution = cma_es::search::<P>(&config, time_limit);
+            evaluations = Some((solution.evaluations, solution.evaluations_to_target));
+            (solution.argument, solution.value)
+        }
+        4 => local_search::tabu::search::<P>(&Default::default(), time_limit),
+        _ => return Err(String::from("Incorrect algorithm (accepted 0 to 4)")),
+    };
 
     let elapsed = Instant::now().duration_since(start);
 
-    (arg, val, elapsed)
+    Ok((arg, val, elapsed, evaluations))
 }
 
 fn main_interactive() -> Result<(), String> {
@@ -28,21 +58,38 @@ fn main_interactive() -> Result<(), String> {
 
     // eprintln!("{:?}", input);
 
+    let mut input = input.split_ascii_whitespace();
+
     let parsed = input
-        .split_ascii_whitespace()
+        .by_ref()
+        .take(3)
         .map(str::parse)
         .collect::<Result<Vec<u64>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let (time, choice) = match *parsed.as_slice() {
-        [time, choice] if time > 0 => (time, choice),
+    let target = input
+        .next()
+        .map(str::parse::<f64>)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let (time, choice, algorithm) = match (parsed.as_slice(), target) {
+        (&[time, choice], None) if time > 0 => (time, choice, 0),
+        (&[time, choice, algorithm], None) if time > 0 => (time, choice, algorithm),
+        (&[time, choice, 3], Some(_)) if time > 0 => (time, choice, 3),
         _ => return Err(String::from("Incorrect arguments")),
     };
 
-    let (arg, val, _elapsed) = match choice {
-        0 => do_search::<HappyCat>(Duration::from_secs(time)),
-        1 => do_search::<Griewank>(Duration::from_secs(time)),
-        _ => return Err(String::from("Incorrect choice (accepted 0 or 1)")),
+    if input.next().is_some() {
+        return Err(String::from("Incorrect arguments"));
+    }
+
+    let duration = Duration::from_secs(time);
+    let (arg, val, _elapsed, evaluations) = match choice {
+        0 => do_search::<HappyCat>(algorithm, duration, target)?,
+        1 => do_search::<Griewank>(algorithm, duration, target)?,
+        2 => do_search::<Salomon>(algorithm, duration, target)?,
+        _ => return Err(String::from("Incorrect choice (accepted 0, 1 or 2)")),
     };
 
     for x in arg.iter() {
@@ -51,6 +98,14 @@ fn main_interactive() -> Result<(), String> {
 
     print!("{}", val);
 
+    if let Some((total, to_target)) = evaluations {
+        println!();
+        match to_target {
+            Some(to_target) => print!("{} {}", total, to_target),
+            None => print!("{} -", total),
+        }
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,204 @@
+use crate::local_search::cmp_partial;
+use crate::local_search::common::{clamp_vector4, length_range_inclusive};
+use crate::local_search::problem::{BoxDomain, Domain, Problem};
+use nalgebra::Vector4;
+use rand::prelude::*;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Velocity {
+    /// Inertia weight decreasing linearly from `initial_weight` to `final_weight`
+    /// over the time limit.
+    Inertia {
+        initial_weight: f64,
+        final_weight: f64,
+        cognitive: f64,
+        social: f64,
+    },
+    /// Clerc's constriction coefficient, requires `cognitive + social > 4`.
+    Constriction { cognitive: f64, social: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Global,
+    /// Every particle is informed by `radius` neighbours on each side of a ring.
+    Ring {
+        radius: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub swarm_size: usize,
+    pub velocity: Velocity,
+    pub topology: Topology,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            swarm_size: 40,
+            velocity: Velocity::Constriction {
+                cognitive: 2.05,
+                social: 2.05,
+            },
+            topology: Topology::Ring { radius: 1 },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    position: Vector4<f64>,
+    velocity: Vector4<f64>,
+    best_position: Vector4<f64>,
+    best_value: f64,
+}
+
+fn constriction_coefficient(cognitive: f64, social: f64) -> f64 {
+    let phi = cognitive + social;
+    2.0 / (2.0 - phi - f64::sqrt(phi * phi - 4.0 * phi)).abs()
+}
+
+fn neighbourhood_best(particles: &[Particle], i: usize, topology: Topology) -> usize {
+    let size = particles.len();
+    let neighbours: Box<dyn Iterator<Item = usize>> = match topology {
+        Topology::Global => Box::new(0..size),
+        Topology::Ring { radius } => {
+            let radius = radius.min(size / 2);
+            Box::new((size + i - radius..=size + i + radius).map(move |j| j % size))
+        }
+    };
+
+    neighbours
+        .min_by(|&a, &b| cmp_partial(&particles[a].best_value, &particles[b].best_value))
+        .unwrap()
+}
+
+pub fn search<P>(config: &Config, time_limit: Instant) -> (Vector4<f64>, f64)
+where
+    P: Problem,
+    P::Domain: BoxDomain,
+{
+    let bounds = <P::Domain as BoxDomain>::BOUNDS;
+    let max_speed = length_range_inclusive(&bounds);
+    let rng = &mut thread_rng();
+    let size = config.swarm_size.max(2);
+
+    let start = Instant::now();
+    let total = time_limit.saturating_duration_since(start).as_secs_f64();
+
+    let mut particles: Vec<_> = std::iter::repeat_with(|| {
+        let position = P::Domain::random(1.0);
+        let velocity = (P::Domain::random(1.0) - position) * 0.5;
+        let best_value = P::value(&position);
+        Particle {
+            position,
+            velocity,
+            best_position: position,
+            best_value,
+        }
+    })
+    .take(size)
+    .collect();
+
+    while Instant::now() < time_limit {
+        let informants: Vec<_> = (0..size)
+            .map(|i| particles[neighbourhood_best(&particles, i, config.topology)].best_position)
+            .collect();
+
+        let (weight, constriction, cognitive, social) = match config.velocity {
+            Velocity::Inertia {
+                initial_weight,
+                final_weight,
+                cognitive,
+                social,
+            } => {
+                let elapsed = start.elapsed().as_secs_f64() / total;
+                let weight = initial_weight + (final_weight - initial_weight) * elapsed.min(1.0);
+                (weight, 1.0, cognitive, social)
+            }
+            Velocity::Constriction { cognitive, social } => (
+                1.0,
+                constriction_coefficient(cognitive, social),
+                cognitive,
+                social,
+            ),
+        };
+
+        for (particle, informant) in particles.iter_mut().zip(informants) {
+            let r1 = Vector4::from_fn(|_, _| rng.gen::<f64>());
+            let r2 = Vector4::from_fn(|_, _| rng.gen::<f64>());
+
+            let attraction = r1.component_mul(&(particle.best_position - particle.position))
+                * cognitive
+                + r2.component_mul(&(informant - particle.position)) * social;
+            let velocity = (particle.velocity * weight + attraction) * constriction;
+            particle.velocity = velocity.map(|v| v.clamp(-max_speed, max_speed));
+
+            let moved = particle.position + particle.velocity;
+            particle.position = clamp_vector4(&bounds, &moved);
+            particle.velocity =
+                particle
+                    .velocity
+                    .zip_map(&(moved - particle.position), |v, overshoot| {
+                        if overshoot == 0.0 {
+                            v
+                        } else {
+                            0.0
+                        }
+                    });
+
+            let value = P::value(&particle.position);
+            if value < particle.best_value {
+                particle.best_position = particle.position;
+                particle.best_value = value;
+            }
+        }
+    }
+
+    let best = particles
+        .into_iter()
+        .min_by(|a, b| cmp_partial(&a.best_value, &b.best_value))
+        .unwrap();
+
+    (best.best_position, best.best_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_search::sphere::Sphere;
+    use std::time::Duration;
+
+    #[test]
+    fn converges_on_sphere() {
+        let velocities = [
+            Velocity::Inertia {
+                initial_weight: 0.9,
+                final_weight: 0.4,
+                cognitive: 2.0,
+                social: 2.0,
+            },
+            Velocity::Constriction {
+                cognitive: 2.05,
+                social: 2.05,
+            },
+        ];
+        let topologies = [Topology::Global, Topology::Ring { radius: 1 }];
+
+        for &velocity in &velocities {
+            for &topology in &topologies {
+                let config = Config {
+                    swarm_size: 20,
+                    velocity,
+                    topology,
+                };
+                let time_limit = Instant::now() + Duration::from_millis(100);
+                let (_, value) = search::<Sphere>(&config, time_limit);
+                assert!(value < 1e-6, "{:?} {:?}: {}", velocity, topology, value);
+            }
+        }
+    }
+}
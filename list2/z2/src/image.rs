@@ -0,0 +1,428 @@
+use ndarray::prelude::*;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = &[137, 80, 78, 71, 13, 10, 26, 10];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Pgm,
+    Ppm,
+    Png,
+}
+
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pgm" => Some(Format::Pgm),
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub enum ImageError {
+    UnknownFormat,
+    InvalidHeader,
+    InvalidData,
+    NotEnoughData,
+    PngError(String),
+    IOError(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "Unknown image format"),
+            ImageError::InvalidHeader => write!(f, "Invalid image header"),
+            ImageError::InvalidData => write!(f, "Invalid pixel data"),
+            ImageError::NotEnoughData => write!(f, "Not enough pixel data"),
+            ImageError::PngError(e) => write!(f, "{}", e),
+            ImageError::IOError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::IOError(err.to_string())
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        ImageError::PngError(err.to_string())
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        ImageError::PngError(err.to_string())
+    }
+}
+
+/// Whitespace separated tokens of a netpbm header, skipping `#` comments.
+struct Tokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.data.get(self.position) {
+            if c == b'#' {
+                while !matches!(self.data.get(self.position), None | Some(b'\n')) {
+                    self.position += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace();
+        let start = self.position;
+        while matches!(self.data.get(self.position), Some(c) if !c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            None
+        } else {
+            Some(&self.data[start..self.position])
+        }
+    }
+
+    fn next_number(&mut self) -> Option<usize> {
+        std::str::from_utf8(self.next_token()?).ok()?.parse().ok()
+    }
+
+    /// Raster data starting after the single whitespace character ending the header.
+    fn rest(&self) -> &'a [u8] {
+        self.data.get(self.position + 1..).unwrap_or(&[])
+    }
+}
+
+fn scale_to_u8(value: usize, max_value: usize) -> u8 {
+    if max_value == 255 {
+        value as u8
+    } else {
+        ((value * 255 + max_value / 2) / max_value) as u8
+    }
+}
+
+/// Reads `P2`/`P5` (one channel) and `P3`/`P6` (three channels) netpbm images.
+fn read_netpbm(data: &[u8]) -> Result<Array3<u8>, ImageError> {
+    use ImageError::*;
+
+    let mut tokens = Tokens::new(data);
+    let magic = tokens.next_token().ok_or(InvalidHeader)?;
+    let (plain, channels) = match magic {
+        b"P2" => (true, 1),
+        b"P5" => (false, 1),
+        b"P3" => (true, 3),
+        b"P6" => (false, 3),
+        _ => return Err(InvalidHeader),
+    };
+
+    let mut header = || tokens.next_number().ok_or(InvalidHeader);
+    let (width, height, max_value) = (header()?, header()?, header()?);
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(InvalidHeader);
+    }
+    let samples = width * height * channels;
+
+    let values = if plain {
+        (0..samples)
+            .map(|_| match tokens.next_number() {
+                Some(value) if value <= max_value => Ok(scale_to_u8(value, max_value)),
+                Some(_) => Err(InvalidData),
+                None => Err(NotEnoughData),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+        let raster = tokens.rest();
+        if raster.len() < samples * bytes_per_value {
+            return Err(NotEnoughData);
+        }
+
+        raster
+            .chunks_exact(bytes_per_value)
+            .take(samples)
+            .map(|bytes| bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+            .map(|value| {
+                if value <= max_value {
+                    Ok(scale_to_u8(value, max_value))
+                } else {
+                    Err(InvalidData)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(Array3::from_shape_vec((height, width, channels), values).unwrap())
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (0.299 * f64::from(r) + 0.587 * f64::from(g) + 0.114 * f64::from(b)).round() as u8
+}
+
+pub fn to_luma(image: ArrayView3<u8>) -> Array2<u8> {
+    let (height, width, _) = image.dim();
+    Array2::from_shape_fn((height, width), |(i, j)| {
+        luma(image[[i, j, 0]], image[[i, j, 1]], image[[i, j, 2]])
+    })
+}
+
+fn read_png(data: &[u8]) -> Result<Array3<u8>, ImageError> {
+    let (info, mut reader) = png::Decoder::new(data).read_info()?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+
+    let (color_type, _) = reader.output_color_type();
+    let samples = color_type.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = match color_type {
+        png::ColorType::RGB | png::ColorType::RGBA => 3,
+        _ => 1,
+    };
+
+    let values = buffer
+        .chunks_exact(info.line_size)
+        .flat_map(|line| line.chunks_exact(samples).take(width))
+        .flat_map(|pixel| pixel[..channels].iter().copied())
+        .collect();
+
+    Ok(Array3::from_shape_vec((height, width, channels), values).unwrap())
+}
+
+/// Pixels of a PGM, PPM or PNG file as `(height, width, channels)` with one or three channels.
+fn read_image<R: Read>(mut reader: R) -> Result<Array3<u8>, ImageError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    if data.starts_with(PNG_SIGNATURE) {
+        read_png(&data)
+    } else if data.starts_with(b"P") {
+        read_netpbm(&data)
+    } else {
+        Err(ImageError::UnknownFormat)
+    }
+}
+
+/// Reads a grayscale image from a PGM (`P2` or `P5`), PPM (`P3` or `P6`) or PNG file,
+/// colour images are converted to luma.
+pub fn read_gray<R: Read>(reader: R) -> Result<Array2<u8>, ImageError> {
+    let image = read_image(reader)?;
+
+    Ok(match image.len_of(Axis(2)) {
+        1 => image.index_axis_move(Axis(2), 0),
+        _ => to_luma(image.view()),
+    })
+}
+
+/// Reads an RGB image as `(height, width, 3)`, grayscale images are replicated to all channels.
+pub fn read_rgb<R: Read>(reader: R) -> Result<Array3<u8>, ImageError> {
+    let image = read_image(reader)?;
+    let (height, width, channels) = image.dim();
+
+    Ok(match channels {
+        3 => image,
+        _ => Array3::from_shape_fn((height, width, 3), |(i, j, _)| image[[i, j, 0]]),
+    })
+}
+
+pub fn write_pgm<W: Write>(mut writer: W, image: ArrayView2<u8>) -> Result<(), ImageError> {
+    let (height, width) = image.dim();
+    write!(writer, "P5\n{} {}\n255\n", width, height)?;
+    writer.write_all(&image.iter().copied().collect::<Vec<_>>())?;
+
+    Ok(())
+}
+
+pub fn write_png<W: Write>(writer: W, image: ArrayView2<u8>) -> Result<(), ImageError> {
+    let (height, width) = image.dim();
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.iter().copied().collect::<Vec<_>>())?;
+
+    Ok(())
+}
+
+pub fn write_ppm<W: Write>(mut writer: W, image: ArrayView3<u8>) -> Result<(), ImageError> {
+    let (height, width, _) = image.dim();
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    writer.write_all(&image.iter().copied().collect::<Vec<_>>())?;
+
+    Ok(())
+}
+
+pub fn write_png_rgb<W: Write>(writer: W, image: ArrayView3<u8>) -> Result<(), ImageError> {
+    let (height, width, _) = image.dim();
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.iter().copied().collect::<Vec<_>>())?;
+
+    Ok(())
+}
+
+pub fn write_gray<W: Write>(
+    writer: W,
+    format: Format,
+    image: ArrayView2<u8>,
+) -> Result<(), ImageError> {
+    match format {
+        Format::Pgm => write_pgm(writer, image),
+        Format::Ppm => {
+            let (height, width) = image.dim();
+            let rgb = Array3::from_shape_fn((height, width, 3), |(i, j, _)| image[[i, j]]);
+            write_ppm(writer, rgb.view())
+        }
+        Format::Png => write_png(writer, image),
+    }
+}
+
+pub fn write_rgb<W: Write>(
+    writer: W,
+    format: Format,
+    image: ArrayView3<u8>,
+) -> Result<(), ImageError> {
+    match format {
+        Format::Pgm => write_pgm(writer, to_luma(image).view()),
+        Format::Ppm => write_ppm(writer, image),
+        Format::Png => write_png_rgb(writer, image),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Array2<u8> {
+        array![[0, 32, 64], [128, 160, 255]]
+    }
+
+    #[test]
+    fn read_plain_pgm() {
+        let input = "P2\n# comment\n3 2\n255\n0 32 64\n128 160 255\n";
+        assert_eq!(read_gray(input.as_bytes()), Ok(example()));
+    }
+
+    #[test]
+    fn read_plain_pgm_scaled() {
+        let input = "P2 3 2 15 0 2 4 8 10 15";
+        assert_eq!(
+            read_gray(input.as_bytes()),
+            Ok(array![[0, 34, 68], [136, 170, 255]])
+        );
+    }
+
+    #[test]
+    fn read_plain_pgm_not_enough_data() {
+        let input = "P2 3 2 255 0 32 64 128";
+        assert_eq!(read_gray(input.as_bytes()), Err(ImageError::NotEnoughData));
+    }
+
+    #[test]
+    fn read_plain_pgm_value_too_big() {
+        let input = "P2 3 2 15 0 2 4 8 10 16";
+        assert_eq!(read_gray(input.as_bytes()), Err(ImageError::InvalidData));
+    }
+
+    #[test]
+    fn read_pgm_invalid_header() {
+        let input = "P5 3 x 255 ";
+        assert_eq!(read_gray(input.as_bytes()), Err(ImageError::InvalidHeader));
+    }
+
+    #[test]
+    fn read_unknown_format() {
+        let input = "5 16 16 4\n";
+        assert_eq!(read_gray(input.as_bytes()), Err(ImageError::UnknownFormat));
+    }
+
+    #[test]
+    fn pgm_round_trip() {
+        let mut buffer = Vec::new();
+        write_pgm(&mut buffer, example().view()).unwrap();
+        assert_eq!(read_gray(buffer.as_slice()), Ok(example()));
+    }
+
+    #[test]
+    fn read_binary_pgm_truncated() {
+        let mut buffer = Vec::new();
+        write_pgm(&mut buffer, example().view()).unwrap();
+        buffer.pop();
+        assert_eq!(read_gray(buffer.as_slice()), Err(ImageError::NotEnoughData));
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut buffer = Vec::new();
+        write_png(&mut buffer, example().view()).unwrap();
+        assert_eq!(read_gray(buffer.as_slice()), Ok(example()));
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Format::from_path("out.PGM"), Some(Format::Pgm));
+        assert_eq!(Format::from_path("dir/out.png"), Some(Format::Png));
+        assert_eq!(Format::from_path("out.ppm"), Some(Format::Ppm));
+        assert_eq!(Format::from_path("out.txt"), None);
+    }
+
+    fn colour_example() -> Array3<u8> {
+        Array3::from_shape_vec((2, 2, 3), vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 10, 20, 30])
+            .unwrap()
+    }
+
+    #[test]
+    fn read_plain_ppm() {
+        let input = "P3\n2 2\n255\n255 0 0  0 255 0\n0 0 255  10 20 30\n";
+        assert_eq!(read_rgb(input.as_bytes()), Ok(colour_example()));
+        assert_eq!(read_gray(input.as_bytes()), Ok(array![[76, 150], [29, 18]]));
+    }
+
+    #[test]
+    fn read_gray_as_rgb() {
+        let input = "P2 1 2 255 7 9";
+        let image = read_rgb(input.as_bytes()).unwrap();
+        assert_eq!(image.dim(), (2, 1, 3));
+        assert!(image.slice(s![0, 0, ..]).iter().all(|&v| v == 7));
+        assert!(image.slice(s![1, 0, ..]).iter().all(|&v| v == 9));
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut buffer = Vec::new();
+        write_ppm(&mut buffer, colour_example().view()).unwrap();
+        assert_eq!(read_rgb(buffer.as_slice()), Ok(colour_example()));
+    }
+
+    #[test]
+    fn png_rgb_round_trip() {
+        let mut buffer = Vec::new();
+        write_png_rgb(&mut buffer, colour_example().view()).unwrap();
+        assert_eq!(read_rgb(buffer.as_slice()), Ok(colour_example()));
+    }
+}
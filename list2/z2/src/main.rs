@@ -1,13 +1,126 @@
 #![allow(clippy::unit_arg)]
 
-use self::solver::Solver;
-use std::error::Error;
-use std::io::stdin;
-use ndarray::ArrayView2;
+use self::solver::colour::{ColourPalette, ColourSpace};
+use self::solver::metric::{
+    EdgeWeightedError, MeanAbsoluteError, MeanSquaredError, Metric, PeakSignalToNoise,
+    StructuralDissimilarity,
+};
+use self::solver::palette::Palette;
+use self::solver::{Acceptance, Cooling, Solver};
 use crate::solver::Value;
+use ndarray::{Array3, ArrayView2};
+use std::error::Error;
+use std::fs::File;
+use std::io::{stdin, BufReader, BufWriter};
+use std::time::Duration;
+use z2::image::{self, Format};
 
 mod solver;
 
+const USAGE: &str = "Usage: z2 [--palette <v1,v2,...> | --levels <count>] [--optimise-palette] \
+                     [--metric <mse|mae|psnr|ssim|edge>] [--colour <rgb|lab>] \
+                     [--colour-levels <count per channel>] [--guillotine] \
+                     [--greedy | [--temperature <initial>] [--cooling <linear|exponential|geometric>]] \
+                     [<input image> <time> <block size> [<output image>]] \
+                     (mse and psnr fit blocks to the mean, mae to the median, ssim and edge \
+                     score mean fitted blocks; colour mode takes no --metric)";
+
+struct Options {
+    palette: Palette,
+    optimise_palette: bool,
+    metric: Option<Box<dyn Metric>>,
+    colour: Option<ColourSpace>,
+    colour_palette: ColourPalette,
+    guillotine: bool,
+    acceptance: Acceptance,
+    positional: Vec<String>,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, &'static str> {
+    let mut options = Options {
+        palette: Palette::default(),
+        optimise_palette: false,
+        metric: None,
+        colour: None,
+        colour_palette: ColourPalette::Free,
+        guillotine: false,
+        acceptance: Acceptance::default(),
+        positional: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--palette" => {
+                let values = args
+                    .next()
+                    .ok_or(USAGE)?
+                    .split(',')
+                    .map(str::parse::<Value>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| USAGE)?;
+                options.palette = Palette::new(values).ok_or(USAGE)?;
+            }
+            "--levels" => {
+                let levels = args.next().ok_or(USAGE)?.parse().map_err(|_| USAGE)?;
+                options.palette = Palette::uniform(levels).ok_or(USAGE)?;
+            }
+            "--optimise-palette" => options.optimise_palette = true,
+            "--metric" => {
+                options.metric = Some(match args.next().ok_or(USAGE)?.as_str() {
+                    "mse" => Box::new(MeanSquaredError),
+                    "mae" => Box::new(MeanAbsoluteError),
+                    "psnr" => Box::new(PeakSignalToNoise),
+                    "ssim" => Box::new(StructuralDissimilarity::default()),
+                    "edge" => Box::new(EdgeWeightedError::default()),
+                    _ => return Err(USAGE),
+                });
+            }
+            "--colour" => {
+                options.colour = match args.next().ok_or(USAGE)?.as_str() {
+                    "rgb" => Some(ColourSpace::Rgb),
+                    "lab" => Some(ColourSpace::Lab),
+                    _ => return Err(USAGE),
+                };
+            }
+            "--colour-levels" => {
+                let levels = args.next().ok_or(USAGE)?.parse().map_err(|_| USAGE)?;
+                options.colour_palette = ColourPalette::uniform(levels).ok_or(USAGE)?;
+            }
+            "--guillotine" => options.guillotine = true,
+            "--greedy" => options.acceptance = Acceptance::Greedy,
+            "--temperature" => {
+                let temperature = args.next().ok_or(USAGE)?.parse().map_err(|_| USAGE)?;
+                match &mut options.acceptance {
+                    Acceptance::Annealing {
+                        initial_temperature,
+                        ..
+                    } => *initial_temperature = temperature,
+                    Acceptance::Greedy => return Err(USAGE),
+                }
+            }
+            "--cooling" => {
+                let schedule = match args.next().ok_or(USAGE)?.as_str() {
+                    "linear" => Cooling::Linear,
+                    "exponential" => Cooling::Exponential { final_ratio: 1e-3 },
+                    "geometric" => Cooling::Geometric { factor: 0.9999 },
+                    _ => return Err(USAGE),
+                };
+                match &mut options.acceptance {
+                    Acceptance::Annealing { cooling, .. } => *cooling = schedule,
+                    Acceptance::Greedy => return Err(USAGE),
+                }
+            }
+            _ => options.positional.push(arg),
+        }
+    }
+
+    if options.colour.is_some() && options.metric.is_some() {
+        return Err(USAGE);
+    }
+
+    Ok(options)
+}
+
 fn print_mat(arr: ArrayView2<Value>) {
     for row in arr.outer_iter() {
         for value in row {
@@ -17,15 +130,85 @@ fn print_mat(arr: ArrayView2<Value>) {
     }
 }
 
+fn solver_from_args(
+    input: &str,
+    time: &str,
+    block_size: &str,
+) -> Result<(Solver, Array3<u8>), Box<dyn Error>> {
+    let image = image::read_rgb(BufReader::new(File::open(input)?))?;
+    let values = image::to_luma(image.view());
+    let time = time.parse::<u64>().map_err(|_| USAGE)?;
+    let block_size = block_size.parse::<usize>().map_err(|_| USAGE)?;
+
+    let (n, m) = values.dim();
+    if time == 0 || block_size == 0 || block_size > n || block_size > m {
+        return Err(USAGE.into());
+    }
+
+    let solver = Solver::new(values, block_size, Duration::from_secs(time));
+    Ok((solver, image))
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let solver = Solver::try_from_read(stdin().lock())?;
+    let options = parse_options(std::env::args().skip(1))?;
+
+    let (solver, colour_image, output) = match options.positional.as_slice() {
+        [] => (Solver::try_from_read(stdin().lock())?, None, None),
+        [input, time, block_size] => {
+            let (solver, image) = solver_from_args(input, time, block_size)?;
+            (solver, Some(image), None)
+        }
+        [input, time, block_size, output] => {
+            let format = Format::from_path(output).ok_or("Unknown output image format")?;
+            let (solver, image) = solver_from_args(input, time, block_size)?;
+            (solver, Some(image), Some((output, format)))
+        }
+        _ => return Err(USAGE.into()),
+    };
 
-    let (blocks, full_solution) = solver.search();
+    let solver = solver
+        .with_palette(options.palette, options.optimise_palette)
+        .with_metric(options.metric.unwrap_or_else(|| Box::new(MeanSquaredError)))
+        .with_acceptance(options.acceptance);
 
-    println!("{}", full_solution.distance);
+    if let Some(space) = options.colour {
+        let image = colour_image.ok_or("Colour mode needs an input image")?;
+        let solution = solver.search_colour(
+            image.view(),
+            space,
+            &options.colour_palette,
+            options.guillotine,
+        );
 
-    print_mat(blocks.values.view());
-    // print_mat(full_solution.matrix.view());
+        println!("{}", solution.distance);
+
+        if let Some((path, format)) = output {
+            let writer = BufWriter::new(File::create(path)?);
+            image::write_rgb(writer, format, solution.image.view())?;
+        }
+
+        return Ok(());
+    }
+
+    let full_solution = if options.guillotine {
+        let (partition, full_solution) = solver.search_partition();
+        for (rectangle, value) in partition.leaves() {
+            eprintln!("{:?} x {:?}: {}", rectangle.rows, rectangle.cols, value);
+        }
+        full_solution
+    } else {
+        let (blocks, full_solution) = solver.search();
+        print_mat(blocks.values.view());
+        full_solution
+    };
+
+    println!("{}", solver.metric().report(full_solution.distance));
+    eprintln!("palette: {:?}", full_solution.palette.values());
+
+    if let Some((path, format)) = output {
+        let writer = BufWriter::new(File::create(path)?);
+        image::write_gray(writer, format, full_solution.matrix.view())?;
+    }
 
     Ok(())
 }
@@ -1,4 +1,9 @@
 use crate::solver::block_matrix::BlockMatrix;
+use crate::solver::colour::{ColourFit, ColourPalette, ColourSpace, ColourTable};
+use crate::solver::metric::{BlockError, MeanSquaredError, Metric};
+use crate::solver::palette::Palette;
+use crate::solver::partition::Partition;
+use crate::solver::summed_area::SummedAreaTable;
 use itertools::Itertools;
 use ndarray::prelude::*;
 use rand::distributions::Uniform;
@@ -7,24 +12,176 @@ use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fmt;
 use std::io::BufRead;
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
 pub(crate) type Value = u8;
 pub(crate) type ValueMatrix = Array2<Value>;
 pub(crate) type Distance = f64;
 
+const UNIFORM_RESIZE_PROBABILITY: f64 = 0.01;
+
+type Score<'a, L> = &'a dyn Fn(&L, Distance) -> Distance;
+
+/// How the temperature falls from its initial value over the run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Cooling {
+    /// Linearly to zero at the time limit.
+    Linear,
+    /// Exponentially in time, reaching `final_ratio` of the initial temperature at the time limit.
+    Exponential { final_ratio: f64 },
+    /// Multiplied by `factor` after every iteration.
+    Geometric { factor: f64 },
+}
+
+impl Cooling {
+    fn temperature(self, initial: f64, progress: f64, iteration: u32) -> f64 {
+        match self {
+            Cooling::Linear => initial * (1.0 - progress).max(0.0),
+            Cooling::Exponential { final_ratio } => initial * final_ratio.powf(progress.min(1.0)),
+            Cooling::Geometric { factor } => initial * factor.powi(iteration as i32),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Acceptance {
+    /// Only strictly improving moves are accepted.
+    Greedy,
+    /// Metropolis criterion, a move worse by `Δ` is accepted with probability `exp(-Δ / T)`.
+    /// The initial temperature is relative to the distance of the initial layout.
+    Annealing {
+        initial_temperature: f64,
+        cooling: Cooling,
+    },
+}
+
+impl Default for Acceptance {
+    fn default() -> Self {
+        Acceptance::Annealing {
+            initial_temperature: 0.05,
+            cooling: Cooling::Exponential { final_ratio: 1e-3 },
+        }
+    }
+}
+
 mod block_matrix;
+pub(crate) mod colour;
+pub(crate) mod metric;
+pub(crate) mod palette;
+pub(crate) mod partition;
+mod summed_area;
+
+/// Chooses the value of a single block.
+pub(crate) trait Fit {
+    /// Value minimising the error of the block covering `rows × cols`, together with
+    /// the block's contribution to the distance.
+    fn fit(&self, rows: Range<usize>, cols: Range<usize>) -> (Value, Distance);
+}
 
-#[derive(Debug, Clone)]
+/// Nearest palette entry to the block's mean, which minimises the block's squared error, or
+/// with the image pixels given, the entry minimising the block's absolute error.
+pub(crate) struct GrayFit<'a> {
+    palette: &'a Palette,
+    table: &'a SummedAreaTable,
+    values: Option<&'a ValueMatrix>,
+    recip: f64,
+}
+
+impl<'a> GrayFit<'a> {
+    pub fn new(palette: &'a Palette, table: &'a SummedAreaTable) -> Self {
+        let dim = table.dim();
+        Self {
+            palette,
+            table,
+            values: None,
+            recip: f64::recip((dim[0] * dim[1]) as f64),
+        }
+    }
+
+    /// Fits the absolute error of `values` instead, which takes a pass over the block.
+    pub fn with_absolute_error(mut self, values: &'a ValueMatrix) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    /// The entry nearest to the median or one of its neighbours, as the absolute error is
+    /// convex in the value and smallest at the median.
+    fn fit_absolute(&self, block: ArrayView2<Value>) -> (Value, Distance) {
+        let mut pixels: Vec<_> = block.iter().copied().collect();
+        let middle = pixels.len() / 2;
+        let median = *pixels.select_nth_unstable(middle).1;
+
+        let error = |index: Value| {
+            let value = i32::from(self.palette.value(index));
+            pixels
+                .iter()
+                .map(|&v| (i32::from(v) - value).abs() as f64)
+                .sum::<f64>()
+                * self.recip
+        };
+
+        let nearest = self.palette.nearest(f64::from(median));
+        let last = (self.palette.len() - 1) as Value;
+        (nearest.saturating_sub(1)..=nearest.saturating_add(1).min(last))
+            .map(|index| (index, error(index)))
+            .min_by(|(_, e1), (_, e2)| e1.partial_cmp(e2).unwrap())
+            .unwrap()
+    }
+}
+
+impl Fit for GrayFit<'_> {
+    fn fit(&self, rows: Range<usize>, cols: Range<usize>) -> (Value, Distance) {
+        if let Some(values) = self.values {
+            return self.fit_absolute(values.slice(s![rows, cols]));
+        }
+
+        let statistics = self.table.statistics(rows, cols);
+        let value = self.palette.nearest(statistics.mean());
+        let error = statistics.squared_error(self.palette.value(value) as f64) * self.recip;
+        (value, error)
+    }
+}
+
+/// A division of the image into blocks, each filled with one value.
+pub(crate) trait Layout: Clone {
+    /// Image rows, columns and value of every block.
+    fn blocks(&self) -> Vec<(Range<usize>, Range<usize>, Value)>;
+
+    /// Mean intensity and pixel count of every block.
+    fn block_means(&self, table: &SummedAreaTable) -> Vec<(f64, f64)> {
+        self.blocks()
+            .into_iter()
+            .map(|(rows, cols, _)| {
+                let statistics = table.statistics(rows, cols);
+                (statistics.mean(), statistics.count)
+            })
+            .collect()
+    }
+
+    /// Fits every block and returns the resulting distance.
+    fn optimise_values(&mut self, fit: &impl Fit) -> Distance;
+
+    fn to_full_size(&self, palette: &Palette, dim: Ix2) -> ValueMatrix;
+}
+
+#[derive(Debug)]
 pub(crate) struct Solver {
     values: ValueMatrix,
     minimal_block_size: usize,
     time_limit: Duration,
+    palette: Palette,
+    optimise_palette: bool,
+    metric: Box<dyn Metric>,
+    acceptance: Acceptance,
 }
 
 #[derive(Debug, Clone)]
-struct Solution {
-    matrix: BlockMatrix,
+struct Solution<L> {
+    matrix: L,
+    /// Sum of the block errors computed by the fit.
+    fitted: Distance,
+    /// Objective of the search, `fitted` unless the metric requires rendering the layout.
     distance: Distance,
 }
 
@@ -32,19 +189,28 @@ struct Solution {
 pub(crate) struct FullSizeSolution {
     pub matrix: ValueMatrix,
     pub distance: Distance,
+    pub palette: Palette,
 }
 
-impl Solution {
-    pub fn new(matrix: BlockMatrix, distance: Distance) -> Self {
-        Self { matrix, distance }
-    }
+#[derive(Debug, Clone)]
+pub(crate) struct ColourSolution {
+    pub image: Array3<u8>,
+    pub distance: Distance,
+}
 
-    fn to_full_size(&self, dim: Ix2) -> FullSizeSolution {
-        FullSizeSolution {
-            matrix: self.matrix.to_full_size(dim),
-            distance: self.distance,
+impl<L: Layout> Solution<L> {
+    pub fn new(matrix: L, fitted: Distance) -> Self {
+        Self {
+            matrix,
+            fitted,
+            distance: fitted,
         }
     }
+
+    fn optimised(mut matrix: L, fit: &impl Fit) -> Self {
+        let fitted = matrix.optimise_values(fit);
+        Self::new(matrix, fitted)
+    }
 }
 
 impl Solver {
@@ -53,33 +219,92 @@ impl Solver {
             values,
             minimal_block_size: block_size,
             time_limit,
+            palette: Palette::default(),
+            optimise_palette: false,
+            metric: Box::new(MeanSquaredError),
+            acceptance: Acceptance::default(),
         }
     }
 
+    /// Uses `palette` for the block values, with `optimise` it is only the starting point
+    /// of k-means run on the block means of the best layout found.
+    pub fn with_palette(mut self, palette: Palette, optimise: bool) -> Self {
+        self.palette = palette;
+        self.optimise_palette = optimise;
+        self
+    }
+
+    /// Measures grayscale approximations with `metric` instead of the mean squared error.
+    /// Block values are fitted for its [`BlockError`], metrics without one (SSIM, edge
+    /// weighted error) keep the mean squared error fits. Colour searches ignore the metric.
+    pub fn with_metric(mut self, metric: Box<dyn Metric>) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    pub fn with_acceptance(mut self, acceptance: Acceptance) -> Self {
+        self.acceptance = acceptance;
+        self
+    }
+
+    pub fn metric(&self) -> &dyn Metric {
+        self.metric.as_ref()
+    }
+
     fn randomly_better(
         current: Distance,
         next: Distance,
         temperature: f64,
         rng: &mut impl Rng,
     ) -> bool {
-        f64::exp((next - current) / temperature) < rng.gen()
+        temperature > 0.0 && rng.gen::<f64>() < f64::exp((current - next) / temperature)
     }
 
-    pub fn search(&self) -> (BlockMatrix, FullSizeSolution) {
-        let start_time = Instant::now();
+    /// Block fit for the metric's block error.
+    fn gray_fit<'a>(&'a self, palette: &'a Palette, table: &'a SummedAreaTable) -> GrayFit<'a> {
+        let fit = GrayFit::new(palette, table);
+        match self.metric.block_error() {
+            Some(BlockError::Absolute) => fit.with_absolute_error(&self.values),
+            Some(BlockError::Squared) | None => fit,
+        }
+    }
+
+    /// Objective of a layout with values fitted for `palette`.
+    fn score<L: Layout>(&self, layout: &L, palette: &Palette, fitted: Distance) -> Distance {
+        if self.metric.block_error().is_some() {
+            fitted
+        } else {
+            let full_size = layout.to_full_size(palette, self.values.raw_dim());
+            self.metric.distance(&self.values, &full_size)
+        }
+    }
 
-        let (h, w) = self.values.dim();
-        let values = &self.values;
+    /// Local search over layouts produced and fitted by `neighbour` and then scored, the
+    /// block values are always optimal for the layout so only the layout itself is searched.
+    fn descend<L, F, N>(
+        &self,
+        initial: L,
+        fit: &F,
+        score: Score<L>,
+        mut neighbour: N,
+    ) -> Solution<L>
+    where
+        L: Layout,
+        F: Fit,
+        N: FnMut(&Solution<L>, &mut ThreadRng) -> Option<Solution<L>>,
+    {
+        let start_time = Instant::now();
+        let scored = |mut solution: Solution<L>| {
+            solution.distance = score(&solution.matrix, solution.fitted);
+            solution
+        };
 
-        let initial = BlockMatrix::zeros(self.minimal_block_size, self.minimal_block_size, h, w);
-        let initial_distance = initial.distance_from(values);
-        let mut best = Solution::new(initial, initial_distance);
+        let mut best = scored(Solution::optimised(initial, fit));
 
         let mut current = best.clone();
+        let initial_distance = best.distance;
 
         let rng = &mut thread_rng();
-        let block_height_dist = Uniform::new(self.minimal_block_size, h);
-        let block_width_dist = Uniform::new(self.minimal_block_size, w);
 
         let iter = std::iter::from_fn({
             let time_limit = self.time_limit;
@@ -94,53 +319,198 @@ impl Solver {
                         elapsed / iters
                     );
                 }
+                let iteration = iters - 1;
                 iters += 1;
                 if elapsed < time_limit {
-                    Some(())
+                    Some((iteration, elapsed.as_secs_f64() / time_limit.as_secs_f64()))
                 } else {
                     None
                 }
             }
         });
 
-        let mut size_tabu = HashSet::new();
-        let mut fail_counter = 0;
+        for (iteration, progress) in iter {
+            let next = match neighbour(&current, rng) {
+                Some(next) => scored(next),
+                None => continue,
+            };
 
-        for () in iter {
-            let blocks = current.matrix.values.dim();
-            let block_count = blocks.0 * blocks.1;
+            let accepted = next.distance < current.distance
+                || match self.acceptance {
+                    Acceptance::Greedy => false,
+                    Acceptance::Annealing {
+                        initial_temperature,
+                        cooling,
+                    } => {
+                        let initial = initial_temperature * initial_distance;
+                        let temperature = cooling.temperature(initial, progress, iteration);
+                        Self::randomly_better(current.distance, next.distance, temperature, rng)
+                    }
+                };
+
+            if accepted {
+                current = next;
+                if current.distance < best.distance {
+                    best = current.clone();
+                }
+            }
+        }
+
+        best
+    }
 
-            let next = if rng.gen_bool(0.01) || fail_counter > block_count * 10 {
-                size_tabu.insert(blocks);
+    /// Searches grids of row and column cuts: mostly by moving single cuts, sometimes by
+    /// jumping to an untried uniform grid.
+    fn search_grid(
+        &self,
+        fit: &impl Fit,
+        score: Score<BlockMatrix>,
+        (h, w): (usize, usize),
+    ) -> Solution<BlockMatrix> {
+        let minimal_block_size = self.minimal_block_size;
+        let initial = BlockMatrix::zeros(minimal_block_size, minimal_block_size, h, w);
 
-                let sizes = std::iter::repeat_with(|| {
-                    (rng.sample(block_height_dist), rng.sample(block_width_dist))
-                });
+        let block_height_dist = Uniform::new_inclusive(minimal_block_size, h);
+        let block_width_dist = Uniform::new_inclusive(minimal_block_size, w);
 
-                if let Some((bh, bw)) = sizes.take(w * h).find(|s| !size_tabu.contains(s)) {
-                    current.matrix.with_block_size(bh, bw, h, w)
+        let mut size_tabu = HashSet::new();
+        size_tabu.insert((minimal_block_size, minimal_block_size));
+        let mut sizes_exhausted = false;
+
+        self.descend(
+            initial,
+            fit,
+            score,
+            |current: &Solution<BlockMatrix>, rng| {
+                if !sizes_exhausted && rng.gen_bool(UNIFORM_RESIZE_PROBABILITY) {
+                    let sizes = std::iter::repeat_with(|| {
+                        (rng.sample(block_height_dist), rng.sample(block_width_dist))
+                    });
+
+                    match sizes.take(w * h).find(|s| !size_tabu.contains(s)) {
+                        Some((bh, bw)) => {
+                            size_tabu.insert((bh, bw));
+                            let (next, fitted) =
+                                current.matrix.with_block_size_optimised(bh, bw, fit);
+                            Some(Solution::new(next, fitted))
+                        }
+                        None => {
+                            sizes_exhausted = true;
+                            None
+                        }
+                    }
                 } else {
-                    break;
+                    let axis = Axis(rng.gen_range(0, 2));
+                    let cut_move = current
+                        .matrix
+                        .random_cut_move(axis, minimal_block_size, rng)?;
+                    let (next, fitted) =
+                        current
+                            .matrix
+                            .with_cut_move_optimised(axis, cut_move, fit, current.fitted);
+                    Some(Solution::new(next, fitted))
                 }
-            } else {
-                current.matrix.clone().perturb_values(rng)
-            };
+            },
+        )
+    }
 
-            let next_distance = next.distance_from(values);
-            if next_distance < current.distance {
-                fail_counter = 0;
-                current = Solution::new(next, next_distance);
-                if current.distance < best.distance {
-                    best = current.clone();
-                }
-            } else {
-                fail_counter += 1;
+    /// Searches guillotine partitions by splitting, merging and moving the edges of rectangles.
+    fn search_guillotine(
+        &self,
+        fit: &impl Fit,
+        score: Score<Partition>,
+        (h, w): (usize, usize),
+    ) -> Solution<Partition> {
+        let minimal_block_size = self.minimal_block_size;
+
+        self.descend(
+            Partition::new(h, w),
+            fit,
+            score,
+            |current: &Solution<Partition>, rng| {
+                let partition_move = current.matrix.random_move(minimal_block_size, rng)?;
+                let next = current.matrix.with_move(partition_move);
+                Some(Solution::optimised(next, fit))
+            },
+        )
+    }
+
+    /// Optionally runs k-means on the palette for the best layout and renders it.
+    fn finish<L: Layout>(
+        &self,
+        mut best: Solution<L>,
+        table: &SummedAreaTable,
+    ) -> (L, FullSizeSolution) {
+        let mut palette = self.palette.clone();
+        if self.optimise_palette {
+            let optimised = palette.optimise(&best.matrix.block_means(table));
+            let mut matrix = best.matrix.clone();
+            let fitted = matrix.optimise_values(&self.gray_fit(&optimised, table));
+            let distance = self.score(&matrix, &optimised, fitted);
+
+            if distance <= best.distance {
+                best = Solution {
+                    matrix,
+                    fitted,
+                    distance,
+                };
+                palette = optimised;
             }
         }
 
-        let full_size = best.to_full_size(values.raw_dim());
+        let matrix = best.matrix.to_full_size(&palette, self.values.raw_dim());
+        let full_size = FullSizeSolution {
+            distance: self.metric.distance(&self.values, &matrix),
+            matrix,
+            palette,
+        };
         (best.matrix, full_size)
     }
+
+    pub fn search(&self) -> (BlockMatrix, FullSizeSolution) {
+        let table = SummedAreaTable::new(&self.values);
+        let fit = self.gray_fit(&self.palette, &table);
+        let score = |layout: &BlockMatrix, fitted| self.score(layout, &self.palette, fitted);
+
+        let best = self.search_grid(&fit, &score, self.values.dim());
+        self.finish(best, &table)
+    }
+
+    pub fn search_partition(&self) -> (Partition, FullSizeSolution) {
+        let table = SummedAreaTable::new(&self.values);
+        let fit = self.gray_fit(&self.palette, &table);
+        let score = |layout: &Partition, fitted| self.score(layout, &self.palette, fitted);
+
+        let best = self.search_guillotine(&fit, &score, self.values.dim());
+        self.finish(best, &table)
+    }
+
+    /// Approximates the colour `image` instead of the grayscale values, which only have to
+    /// match its dimensions. The metric is ignored, colours are compared in `space`.
+    pub fn search_colour(
+        &self,
+        image: ArrayView3<u8>,
+        space: ColourSpace,
+        palette: &ColourPalette,
+        guillotine: bool,
+    ) -> ColourSolution {
+        let (h, w, _) = image.dim();
+        let table = ColourTable::new(image, space);
+        let fit = ColourFit::new(&table, palette);
+
+        let rendered = if guillotine {
+            let best = self.search_guillotine(&fit, &|_, fitted| fitted, (h, w));
+            fit.render(&best.matrix)
+        } else {
+            let best = self.search_grid(&fit, &|_, fitted| fitted, (h, w));
+            fit.render(&best.matrix)
+        };
+
+        ColourSolution {
+            distance: colour::distance(space, image, rendered.view()),
+            image: rendered,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
@@ -306,4 +676,84 @@ mod tests {
             assert_eq!(res.err(), Some(SolverCreationError::InvalidLine))
         }
     }
+
+    mod annealing {
+        use super::*;
+        use std::fs::File;
+        use std::io::BufReader;
+
+        fn solve(name: &str, acceptance: Acceptance) -> Distance {
+            let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name);
+            let mut solver = Solver::try_from_read(BufReader::new(File::open(path).unwrap()))
+                .unwrap()
+                .with_acceptance(acceptance);
+            solver.time_limit = Duration::from_millis(200);
+
+            solver.search().1.distance
+        }
+
+        #[test]
+        fn randomly_better() {
+            let rng = &mut thread_rng();
+            assert!(!Solver::randomly_better(1.0, 2.0, 0.0, rng));
+            assert!((0..100).all(|_| Solver::randomly_better(1.0, 1.0 + 1e-9, 1e3, rng)));
+            assert!((0..100).all(|_| !Solver::randomly_better(1.0, 1e3, 1e-3, rng)));
+        }
+
+        #[test]
+        fn cooling_schedules() {
+            assert_eq!(Cooling::Linear.temperature(2.0, 0.25, 10), 1.5);
+            assert_eq!(Cooling::Linear.temperature(2.0, 1.5, 10), 0.0);
+            let exponential = Cooling::Exponential { final_ratio: 0.01 };
+            assert!((exponential.temperature(2.0, 0.5, 10) - 0.2).abs() < 1e-12);
+            let geometric = Cooling::Geometric { factor: 0.5 };
+            assert_eq!(geometric.temperature(2.0, 0.5, 3), 0.25);
+        }
+
+        #[test]
+        fn annealing_beats_greedy_on_test_inputs() {
+            let names = [
+                "test1.txt",
+                "test2.txt",
+                "test3.txt",
+                "test4.txt",
+                "test5.txt",
+            ];
+            let distances: Vec<_> = names
+                .iter()
+                .map(|name| {
+                    let greedy = solve(name, Acceptance::Greedy);
+                    let annealing = solve(name, Acceptance::default());
+                    assert!(annealing <= greedy, "{}: {} > {}", name, annealing, greedy);
+                    (greedy, annealing)
+                })
+                .collect();
+
+            // greedy descent gets stuck in the starting grid's local optimum on these two
+            for (name, (greedy, annealing)) in names.iter().zip(&distances).take(2) {
+                assert!(annealing < greedy, "{}: {} >= {}", name, annealing, greedy);
+            }
+        }
+    }
+
+    mod colour {
+        use super::*;
+
+        #[test]
+        fn search_colour_grid_changes_block_size() {
+            let image = Array3::from_shape_fn((6, 8, 3), |(i, j, k)| match (j < 5, k) {
+                (true, 0) => 250 - 10 * i as u8,
+                (false, 1) => 40 * i as u8,
+                _ => 30,
+            });
+            let palette = ColourPalette::uniform(3).unwrap();
+            let solver = Solver::new(Array2::zeros((6, 8)), 1, Duration::from_millis(200));
+
+            // every one of the 48 uniform grids gets tried in this time, each fitted with
+            // palette indices beyond the grayscale palette
+            let solution = solver.search_colour(image.view(), ColourSpace::Rgb, &palette, false);
+            assert_eq!(solution.image.dim(), image.dim());
+            assert!(solution.distance.is_finite());
+        }
+    }
 }
@@ -1,42 +1,68 @@
-use super::{Distance, Value};
+use super::palette::Palette;
+use super::{Distance, Fit, Layout, Value};
 use ndarray::prelude::*;
+use ndarray::Slice;
 use rand::prelude::*;
-use rand_distr::Normal;
-use z2::util;
-
-const ALLOWED_VALUES: &[Value] = &[0, 32, 64, 128, 160, 192, 223, 255];
+use std::ops::Range;
 
+/// Blocks laid out on a grid with arbitrary row and column cuts, `row_bounds` and `col_bounds`
+/// hold the first image row (column) of every block followed by the image height (width).
 #[derive(Debug, Clone)]
 pub(crate) struct BlockMatrix {
     pub values: Array2<Value>,
-    pub block_height: usize,
-    pub block_width: usize,
+    pub row_bounds: Vec<usize>,
+    pub col_bounds: Vec<usize>,
+    /// Contribution of every block to the distance, valid after `optimise_values`.
+    errors: Array2<Distance>,
 }
 
 macro_rules! process_blocks_decl {
 ($name:ident [$($mut_:tt)?] $slice:ident => $view:ty) => {
-    fn $name<'a, F>(&self, full_size: &'a $($mut_)? Array2<Value>, mut f: F)
+    fn $name<F>(&self, full_size: &$($mut_)? Array2<Value>, mut f: F)
     where
         F: FnMut(Value, $view),
     {
-        let (h, w) = self.values.dim();
-        let (bh, bw) = (self.block_height, self.block_width);
-        let (n, m) = full_size.dim();
-
-        let is = util::iter_step(0, bh).take(h);
-        for (i, (last_row, full_i)) in util::iter_signal_last(is).enumerate() {
-            let js = util::iter_step(0, bw).take(w);
-            for (j, (last_col, full_j)) in util::iter_signal_last(js).enumerate() {
-                let i_bound = if last_row { n } else { full_i + bh };
-                let j_bound = if last_col { m } else { full_j + bw };
-
-                f(self.values[[i, j]], full_size.$slice(s![full_i..i_bound, full_j..j_bound]));
-            }
+        for (index, rows, cols) in block_ranges(&self.row_bounds, &self.col_bounds) {
+            f(self.values[index], full_size.$slice(s![rows, cols]));
         }
     }
 };
 }
 
+/// Bounds of `outer / block` blocks of size `block`, the last one absorbing the remainder.
+fn uniform_bounds(block: usize, outer: usize) -> Vec<usize> {
+    (0..outer / block)
+        .map(|i| i * block)
+        .chain(std::iter::once(outer))
+        .collect()
+}
+
+/// Image rows and columns covered by every block.
+fn block_ranges<'a>(
+    row_bounds: &'a [usize],
+    col_bounds: &'a [usize],
+) -> impl Iterator<Item = ((usize, usize), Range<usize>, Range<usize>)> + 'a {
+    row_bounds
+        .windows(2)
+        .enumerate()
+        .flat_map(move |(i, rows)| {
+            col_bounds
+                .windows(2)
+                .enumerate()
+                .map(move |(j, cols)| ((i, j), rows[0]..rows[1], cols[0]..cols[1]))
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CutMove {
+    /// Moves the cut before block `index` to `position`.
+    Shift { index: usize, position: usize },
+    /// Splits block `index` at `position`.
+    Insert { index: usize, position: usize },
+    /// Merges blocks `index - 1` and `index`.
+    Delete { index: usize },
+}
+
 impl BlockMatrix {
     pub fn zeros(
         block_height: usize,
@@ -44,83 +70,433 @@ impl BlockMatrix {
         outer_height: usize,
         outer_width: usize,
     ) -> Self {
-        let h = outer_height / block_height;
-        let w = outer_width / block_width;
+        let row_bounds = uniform_bounds(block_height, outer_height);
+        let col_bounds = uniform_bounds(block_width, outer_width);
+
+        let dim = (row_bounds.len() - 1, col_bounds.len() - 1);
 
         Self {
-            values: Array2::zeros((h, w)),
-            block_height,
-            block_width,
+            values: Array2::zeros(dim),
+            row_bounds,
+            col_bounds,
+            errors: Array2::zeros(dim),
         }
     }
 
-    process_blocks_decl!(process_blocks[] slice => ArrayView2<Value>);
     process_blocks_decl!(process_blocks_mut[mut] slice_mut => ArrayViewMut2<Value>);
 
-    pub fn to_full_size(&self, dim: Ix2) -> Array2<Value> {
+    fn bounds(&self, axis: Axis) -> &[usize] {
+        match axis {
+            Axis(0) => &self.row_bounds,
+            _ => &self.col_bounds,
+        }
+    }
+
+    fn bounds_mut(&mut self, axis: Axis) -> &mut Vec<usize> {
+        match axis {
+            Axis(0) => &mut self.row_bounds,
+            _ => &mut self.col_bounds,
+        }
+    }
+
+    /// A random cut move along `axis` keeping every block at least `minimal_block_size` long.
+    pub fn random_cut_move(
+        &self,
+        axis: Axis,
+        minimal_block_size: usize,
+        rng: &mut impl Rng,
+    ) -> Option<CutMove> {
+        let bounds = self.bounds(axis);
+        let blocks = bounds.len() - 1;
+
+        match rng.gen_range(0, 3) {
+            0 if blocks > 1 => {
+                let index = rng.gen_range(1, blocks);
+                let low = bounds[index - 1] + minimal_block_size;
+                let high = bounds[index + 1] - minimal_block_size;
+                let position = rng.gen_range(low, high + 1);
+                if position == bounds[index] {
+                    None
+                } else {
+                    Some(CutMove::Shift { index, position })
+                }
+            }
+            1 => {
+                let index = rng.gen_range(0, blocks);
+                let (start, end) = (bounds[index], bounds[index + 1]);
+                if end - start < 2 * minimal_block_size {
+                    None
+                } else {
+                    let position =
+                        rng.gen_range(start + minimal_block_size, end - minimal_block_size + 1);
+                    Some(CutMove::Insert { index, position })
+                }
+            }
+            2 if blocks > 1 => Some(CutMove::Delete {
+                index: rng.gen_range(1, blocks),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn with_cut_move(&self, axis: Axis, cut_move: CutMove) -> Self {
+        let mut new = self.clone();
+        let blocks = self.values.len_of(axis);
+
+        match cut_move {
+            CutMove::Shift { index, position } => {
+                new.bounds_mut(axis)[index] = position;
+            }
+            CutMove::Insert { index, position } => {
+                new.bounds_mut(axis).insert(index + 1, position);
+                let indices: Vec<_> = (0..=index).chain(index..blocks).collect();
+                new.values = self.values.select(axis, &indices);
+                new.errors = self.errors.select(axis, &indices);
+            }
+            CutMove::Delete { index } => {
+                new.bounds_mut(axis).remove(index);
+                let indices: Vec<_> = (0..blocks).filter(|&i| i != index).collect();
+                new.values = self.values.select(axis, &indices);
+                new.errors = self.errors.select(axis, &indices);
+            }
+        }
+
+        new
+    }
+
+    /// Applies `cut_move` to a matrix with optimised values and distance `distance`,
+    /// re-optimising only the blocks between the cuts it touched.
+    pub fn with_cut_move_optimised(
+        &self,
+        axis: Axis,
+        cut_move: CutMove,
+        fit: &impl Fit,
+        distance: Distance,
+    ) -> (Self, Distance) {
+        let (removed, added) = match cut_move {
+            CutMove::Shift { index, .. } => (index - 1..index + 1, index - 1..index + 1),
+            CutMove::Insert { index, .. } => (index..index + 1, index..index + 2),
+            CutMove::Delete { index } => (index - 1..index + 1, index - 1..index),
+        };
+
+        let mut new = self.with_cut_move(axis, cut_move);
+        let removed = self.errors.slice_axis(axis, Slice::from(removed)).sum();
+        let added = new.optimise_bands(axis, added, fit);
+
+        (new, distance - removed + added)
+    }
+
+    /// A uniform grid of `block_height × block_width` blocks over the same image, the last row
+    /// and column of blocks absorbing the remainder, with values fitted by `fit`.
+    pub fn with_block_size_optimised(
+        &self,
+        block_height: usize,
+        block_width: usize,
+        fit: &impl Fit,
+    ) -> (Self, Distance) {
+        let (outer_height, outer_width) = (
+            *self.row_bounds.last().unwrap(),
+            *self.col_bounds.last().unwrap(),
+        );
+        let mut new = Self::zeros(block_height, block_width, outer_height, outer_width);
+        let distance = new.optimise_values(fit);
+
+        (new, distance)
+    }
+
+    fn optimise_block(&mut self, (i, j): (usize, usize), fit: &impl Fit) -> Distance {
+        let rows = self.row_bounds[i]..self.row_bounds[i + 1];
+        let cols = self.col_bounds[j]..self.col_bounds[j + 1];
+
+        let (value, error) = fit.fit(rows, cols);
+        self.values[[i, j]] = value;
+        self.errors[[i, j]] = error;
+
+        error
+    }
+
+    /// Optimises the blocks in `bands` along `axis`, returning their part of the distance.
+    fn optimise_bands(&mut self, axis: Axis, bands: Range<usize>, fit: &impl Fit) -> Distance {
+        let across = self.values.len_of(Axis(1 - axis.index()));
+
+        bands
+            .flat_map(|band| {
+                (0..across).map(move |other| match axis {
+                    Axis(0) => (band, other),
+                    _ => (other, band),
+                })
+            })
+            .map(|index| self.optimise_block(index, fit))
+            .sum()
+    }
+}
+
+impl Layout for BlockMatrix {
+    fn blocks(&self) -> Vec<(Range<usize>, Range<usize>, Value)> {
+        block_ranges(&self.row_bounds, &self.col_bounds)
+            .map(|(index, rows, cols)| (rows, cols, self.values[index]))
+            .collect()
+    }
+
+    fn optimise_values(&mut self, fit: &impl Fit) -> Distance {
+        let rows = self.values.nrows();
+        self.optimise_bands(Axis(0), 0..rows, fit)
+    }
+
+    fn to_full_size(&self, palette: &Palette, dim: Ix2) -> Array2<Value> {
         let mut arr = Array2::zeros(dim);
 
         self.process_blocks_mut(&mut arr, |val, mut block| {
-            let val = ALLOWED_VALUES[val as usize];
+            let val = palette.value(val);
             block.fill(val);
         });
 
         arr
     }
+}
 
-    pub fn perturb_values(mut self, rng: &mut impl Rng) -> Self {
-        let (h, w) = self.values.dim();
-        let i = rng.gen_range(0, h);
-        let j = rng.gen_range(0, w);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::metric::{MeanAbsoluteError, MeanSquaredError, Metric};
+    use crate::solver::summed_area::SummedAreaTable;
+    use crate::solver::GrayFit;
 
-        let normal = Normal::new(0.0_f64, 2.0).unwrap();
+    fn mean_squared_error(
+        layout: &impl Layout,
+        palette: &Palette,
+        values: &Array2<Value>,
+    ) -> Distance {
+        MeanSquaredError.distance(values, &layout.to_full_size(palette, values.raw_dim()))
+    }
 
-        let block_value = &mut self.values[[i, j]];
-        let moved = rng.sample(normal).round() as i8 + (*block_value as i8);
-        *block_value = util::clamp(moved, 0, ALLOWED_VALUES.len() as i8 - 1) as u8;
+    fn example() -> Array2<Value> {
+        array![
+            [0, 10, 200, 210, 220],
+            [20, 30, 230, 240, 250],
+            [100, 100, 50, 50, 60],
+        ]
+    }
+
+    #[test]
+    fn optimise_values_picks_nearest_to_mean() {
+        let values = example();
+        let palette = Palette::default();
+        let mut matrix = BlockMatrix::zeros(2, 2, 3, 5);
+        matrix.optimise_values(&GrayFit::new(&palette, &SummedAreaTable::new(&values)));
 
-        self
+        let means = [260.0 / 6.0, 1310.0 / 9.0];
+        assert_eq!(
+            matrix.values,
+            array![[palette.nearest(means[0]), palette.nearest(means[1])]]
+        );
     }
 
-    pub fn with_block_size(
-        &self,
-        block_height: usize,
-        block_width: usize,
-        outer_height: usize,
-        outer_width: usize,
-    ) -> Self {
-        let (current_height, current_width) = self.values.dim();
+    #[test]
+    fn optimise_values_distance_matches_distance_from() {
+        let values = example();
+        let table = SummedAreaTable::new(&values);
+        let palette = Palette::default();
+        let fit = GrayFit::new(&palette, &table);
 
-        let mut new = Self::zeros(block_height, block_width, outer_height, outer_width);
-        let (new_height, new_width) = new.values.dim();
+        for bh in 1..=3 {
+            for bw in 1..=5 {
+                let mut matrix = BlockMatrix::zeros(bh, bw, 3, 5);
+                let distance = matrix.optimise_values(&fit);
 
-        let height = std::cmp::min(new_height, current_height);
-        let width = std::cmp::min(new_width, current_width);
+                assert!((distance - mean_squared_error(&matrix, &palette, &values)).abs() < 1e-9);
+            }
+        }
+    }
 
-        let slice = s![..height, ..width];
-        new.values
-            .slice_mut(slice)
-            .assign(&self.values.slice(slice));
+    #[test]
+    fn optimise_values_absolute_error_matches_best_entry() {
+        let values = example();
+        let table = SummedAreaTable::new(&values);
+        let palette = Palette::uniform(5).unwrap();
+        let fit = GrayFit::new(&palette, &table).with_absolute_error(&values);
 
-        new
+        for bh in 1..=3 {
+            for bw in 1..=5 {
+                let mut matrix = BlockMatrix::zeros(bh, bw, 3, 5);
+                let distance = matrix.optimise_values(&fit);
+
+                let full_size = matrix.to_full_size(&palette, values.raw_dim());
+                assert!((distance - MeanAbsoluteError.distance(&values, &full_size)).abs() < 1e-9);
+
+                for (rows, cols, value) in matrix.blocks() {
+                    let block = values.slice(s![rows, cols]);
+                    let error = |index: Value| {
+                        let value = f64::from(palette.value(index));
+                        block
+                            .iter()
+                            .map(|&v| (f64::from(v) - value).abs())
+                            .sum::<f64>()
+                    };
+                    let best = (0..palette.len() as Value)
+                        .map(error)
+                        .fold(f64::MAX, f64::min);
+                    assert_eq!(error(value), best);
+                }
+            }
+        }
     }
 
-    pub fn distance_from(&self, other: &Array2<Value>) -> Distance {
-        let (n, m) = other.dim();
-        let recip = f64::recip((n * m) as f64);
+    fn assert_valid(matrix: &BlockMatrix, minimal_block_size: usize, dim: (usize, usize)) {
+        for (bounds, outer) in [(&matrix.row_bounds, dim.0), (&matrix.col_bounds, dim.1)].iter() {
+            assert_eq!(bounds.first(), Some(&0));
+            assert_eq!(bounds.last(), Some(outer));
+            assert!(bounds.windows(2).all(|b| b[1] - b[0] >= minimal_block_size));
+        }
+        assert_eq!(
+            matrix.values.dim(),
+            (matrix.row_bounds.len() - 1, matrix.col_bounds.len() - 1)
+        );
+    }
 
-        let mut distance = 0.0_f64;
+    #[test]
+    fn zeros_absorbs_remainder() {
+        let matrix = BlockMatrix::zeros(2, 2, 3, 5);
+        assert_eq!(matrix.row_bounds, vec![0, 3]);
+        assert_eq!(matrix.col_bounds, vec![0, 2, 5]);
+        assert_eq!(matrix.values.dim(), (1, 2));
+    }
 
-        self.process_blocks(other, |val, block| {
-            let val = ALLOWED_VALUES[val as usize];
+    /// Distance of the current values, without re-fitting them.
+    fn block_distance(
+        matrix: &BlockMatrix,
+        palette: &Palette,
+        table: &SummedAreaTable,
+    ) -> Distance {
+        let (n, m) = table.dim().into_pattern();
+        matrix
+            .blocks()
+            .into_iter()
+            .map(|(rows, cols, value)| {
+                let value = f64::from(palette.value(value));
+                table.statistics(rows, cols).squared_error(value)
+            })
+            .sum::<f64>()
+            / (n * m) as f64
+    }
 
-            distance += block
-                .iter()
-                .map(|&v| v as f64 - val as f64)
-                .map(|v| v * v * recip)
-                .sum::<f64>()
-        });
+    #[test]
+    fn with_block_size_optimised_matches_distance_for_all_shapes() {
+        for &(n, m) in &[(3, 5), (7, 4), (5, 11), (10, 9)] {
+            let values = Array2::from_shape_fn((n, m), |(i, j)| {
+                ((i * 37 + j * 91 + i * j * 13) % 256) as Value
+            });
+            let table = SummedAreaTable::new(&values);
+            let palette = Palette::default();
+            let fit = GrayFit::new(&palette, &table);
 
-        distance
+            let mut matrix = BlockMatrix::zeros(1, 1, n, m);
+            matrix.optimise_values(&fit);
+
+            for bh in 1..=n {
+                for bw in 1..=m {
+                    let (resized, distance) = matrix.with_block_size_optimised(bh, bw, &fit);
+                    assert_valid(&resized, 1, values.dim());
+                    assert_eq!(resized.row_bounds, uniform_bounds(bh, n));
+                    assert_eq!(resized.col_bounds, uniform_bounds(bw, m));
+
+                    let last =
+                        |bounds: &[usize]| bounds[bounds.len() - 1] - bounds[bounds.len() - 2];
+                    assert_eq!(last(&resized.row_bounds), bh + n % bh);
+                    assert_eq!(last(&resized.col_bounds), bw + m % bw);
+
+                    let full_size = mean_squared_error(&resized, &palette, &values);
+                    assert!((distance - full_size).abs() < 1e-9);
+                    assert!((block_distance(&resized, &palette, &table) - full_size).abs() < 1e-9);
+
+                    matrix = resized;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_cut_move() {
+        let mut matrix = BlockMatrix::zeros(1, 2, 3, 6);
+        matrix.values = array![[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+
+        let shifted = matrix.with_cut_move(
+            Axis(1),
+            CutMove::Shift {
+                index: 1,
+                position: 1,
+            },
+        );
+        assert_eq!(shifted.col_bounds, vec![0, 1, 4, 6]);
+        assert_eq!(shifted.values, matrix.values);
+
+        let inserted = matrix.with_cut_move(
+            Axis(1),
+            CutMove::Insert {
+                index: 2,
+                position: 5,
+            },
+        );
+        assert_eq!(inserted.col_bounds, vec![0, 2, 4, 5, 6]);
+        assert_eq!(
+            inserted.values,
+            array![[1, 2, 3, 3], [4, 5, 6, 6], [7, 8, 9, 9]]
+        );
+
+        let deleted = matrix.with_cut_move(Axis(0), CutMove::Delete { index: 1 });
+        assert_eq!(deleted.row_bounds, vec![0, 2, 3]);
+        assert_eq!(deleted.values, array![[1, 2, 3], [7, 8, 9]]);
+    }
+
+    #[test]
+    fn random_cut_moves_keep_blocks_valid() {
+        let values = example();
+        let table = SummedAreaTable::new(&values);
+        let palette = Palette::default();
+        let fit = GrayFit::new(&palette, &table);
+        let rng = &mut thread_rng();
+
+        for &minimal_block_size in &[1, 2] {
+            let mut matrix = BlockMatrix::zeros(minimal_block_size, minimal_block_size, 3, 5);
+
+            for _ in 0..1000 {
+                let axis = Axis(rng.gen_range(0, 2));
+                if let Some(cut_move) = matrix.random_cut_move(axis, minimal_block_size, rng) {
+                    matrix = matrix.with_cut_move(axis, cut_move);
+                }
+                assert_valid(&matrix, minimal_block_size, values.dim());
+
+                let distance = matrix.optimise_values(&fit);
+                assert!((distance - mean_squared_error(&matrix, &palette, &values)).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn with_cut_move_optimised_matches_full_evaluation() {
+        let values = example();
+        let table = SummedAreaTable::new(&values);
+        let palette = Palette::default();
+        let fit = GrayFit::new(&palette, &table);
+        let rng = &mut thread_rng();
+
+        let mut matrix = BlockMatrix::zeros(1, 1, 3, 5);
+        let mut distance = matrix.optimise_values(&fit);
+
+        for _ in 0..1000 {
+            let axis = Axis(rng.gen_range(0, 2));
+            if let Some(cut_move) = matrix.random_cut_move(axis, 1, rng) {
+                let (next, next_distance) =
+                    matrix.with_cut_move_optimised(axis, cut_move, &fit, distance);
+
+                let mut expected = next.clone();
+                let expected_distance = expected.optimise_values(&fit);
+                assert_eq!(next.values, expected.values);
+                assert!((next_distance - expected_distance).abs() < 1e-6);
+
+                matrix = next;
+                distance = next_distance;
+            }
+        }
     }
 }
@@ -0,0 +1,361 @@
+use super::palette::Palette;
+use super::{Distance, Fit, Layout, Value};
+use itertools::iproduct;
+use ndarray::prelude::*;
+use std::ops::Range;
+
+pub(crate) type Colour = [u8; 3];
+type Coordinates = [f64; 3];
+
+/// Space in which colours are averaged and compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColourSpace {
+    Rgb,
+    /// CIE L*a*b* with the D65 white point, distances approximate perceived differences.
+    Lab,
+}
+
+const WHITE_D65: Coordinates = [0.95047, 1.0, 1.08883];
+const LAB_DELTA: f64 = 6.0 / 29.0;
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn lab_f(t: f64) -> f64 {
+    if t > LAB_DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inverse(t: f64) -> f64 {
+    if t > LAB_DELTA {
+        t.powi(3)
+    } else {
+        3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+impl ColourSpace {
+    pub fn coordinates(self, [r, g, b]: Colour) -> Coordinates {
+        match self {
+            ColourSpace::Rgb => [f64::from(r), f64::from(g), f64::from(b)],
+            ColourSpace::Lab => {
+                let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+                let x = 0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b;
+                let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175_0 * b;
+                let z = 0.019_333_9 * r + 0.119_192_0 * g + 0.950_304_1 * b;
+
+                let fx = lab_f(x / WHITE_D65[0]);
+                let fy = lab_f(y / WHITE_D65[1]);
+                let fz = lab_f(z / WHITE_D65[2]);
+                [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+            }
+        }
+    }
+
+    /// The closest colour representable in 24-bit RGB.
+    pub fn colour(self, [c0, c1, c2]: Coordinates) -> Colour {
+        match self {
+            ColourSpace::Rgb => {
+                let channel = |c: f64| c.round().clamp(0.0, 255.0) as u8;
+                [channel(c0), channel(c1), channel(c2)]
+            }
+            ColourSpace::Lab => {
+                let fy = (c0 + 16.0) / 116.0;
+                let fx = fy + c1 / 500.0;
+                let fz = fy - c2 / 200.0;
+                let x = lab_f_inverse(fx) * WHITE_D65[0];
+                let y = lab_f_inverse(fy) * WHITE_D65[1];
+                let z = lab_f_inverse(fz) * WHITE_D65[2];
+
+                [
+                    linear_to_srgb(3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z),
+                    linear_to_srgb(-0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z),
+                    linear_to_srgb(0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z),
+                ]
+            }
+        }
+    }
+}
+
+fn squared_distance(a: &Coordinates, b: &Coordinates) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Colours the blocks may take, `Free` allows any 24-bit colour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ColourPalette {
+    Free,
+    Fixed(Vec<Colour>),
+}
+
+impl ColourPalette {
+    /// `levels` intensities per channel spread evenly over the whole range.
+    pub fn uniform(levels: usize) -> Option<Self> {
+        if levels.pow(3) > usize::from(Value::MAX) + 1 {
+            return None;
+        }
+
+        let palette = Palette::uniform(levels)?;
+        let values = palette.values();
+        let colours = iproduct!(values, values, values)
+            .map(|(&r, &g, &b)| [r, g, b])
+            .collect();
+
+        Some(ColourPalette::Fixed(colours))
+    }
+}
+
+/// Prefix sums of colour coordinates and their squared norms, see `SummedAreaTable`.
+#[derive(Debug, Clone)]
+pub(crate) struct ColourTable {
+    space: ColourSpace,
+    sums: Array3<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ColourStatistics {
+    pub count: f64,
+    pub sum: Coordinates,
+    pub sum_of_squares: f64,
+}
+
+impl ColourStatistics {
+    pub fn mean(&self) -> Coordinates {
+        let [s0, s1, s2] = self.sum;
+        [s0 / self.count, s1 / self.count, s2 / self.count]
+    }
+
+    /// Sum of squared distances between the block's colours and `colour`.
+    pub fn squared_error(&self, colour: &Coordinates) -> f64 {
+        let dot: f64 = self.sum.iter().zip(colour).map(|(s, c)| s * c).sum();
+        let norm: f64 = colour.iter().map(|c| c * c).sum();
+        self.sum_of_squares - 2.0 * dot + self.count * norm
+    }
+}
+
+impl ColourTable {
+    pub fn new(image: ArrayView3<u8>, space: ColourSpace) -> Self {
+        let (n, m, _) = image.dim();
+        let mut sums = Array3::zeros((n + 1, m + 1, 4));
+
+        for i in 0..n {
+            for j in 0..m {
+                let pixel = image.slice(s![i, j, ..]);
+                let coordinates = space.coordinates([pixel[0], pixel[1], pixel[2]]);
+                let norm = coordinates.iter().map(|c| c * c).sum();
+
+                for (k, &value) in coordinates.iter().chain(Some(&norm)).enumerate() {
+                    sums[[i + 1, j + 1, k]] =
+                        value + sums[[i, j + 1, k]] + sums[[i + 1, j, k]] - sums[[i, j, k]];
+                }
+            }
+        }
+
+        Self { space, sums }
+    }
+
+    pub fn dim(&self) -> Ix2 {
+        let (n, m, _) = self.sums.dim();
+        Ix2(n - 1, m - 1)
+    }
+
+    pub fn statistics(&self, rows: Range<usize>, cols: Range<usize>) -> ColourStatistics {
+        let rectangle = |k| {
+            self.sums[[rows.end, cols.end, k]] + self.sums[[rows.start, cols.start, k]]
+                - self.sums[[rows.start, cols.end, k]]
+                - self.sums[[rows.end, cols.start, k]]
+        };
+
+        ColourStatistics {
+            count: (rows.len() * cols.len()) as f64,
+            sum: [rectangle(0), rectangle(1), rectangle(2)],
+            sum_of_squares: rectangle(3),
+        }
+    }
+}
+
+/// Fits palette colours, or the exact mean colour for `ColourPalette::Free`, to blocks.
+pub(crate) struct ColourFit<'a> {
+    table: &'a ColourTable,
+    palette: &'a ColourPalette,
+    coordinates: Vec<Coordinates>,
+    recip: f64,
+}
+
+impl<'a> ColourFit<'a> {
+    pub fn new(table: &'a ColourTable, palette: &'a ColourPalette) -> Self {
+        let coordinates = match palette {
+            ColourPalette::Free => Vec::new(),
+            ColourPalette::Fixed(colours) => colours
+                .iter()
+                .map(|&colour| table.space.coordinates(colour))
+                .collect(),
+        };
+        let dim = table.dim();
+
+        Self {
+            table,
+            palette,
+            coordinates,
+            recip: f64::recip((dim[0] * dim[1]) as f64),
+        }
+    }
+
+    /// Colour of a block fitted with `value`.
+    pub fn colour(&self, rows: Range<usize>, cols: Range<usize>, value: Value) -> Colour {
+        match self.palette {
+            ColourPalette::Free => {
+                let mean = self.table.statistics(rows, cols).mean();
+                self.table.space.colour(mean)
+            }
+            ColourPalette::Fixed(colours) => colours[value as usize],
+        }
+    }
+
+    pub fn render(&self, layout: &impl Layout) -> Array3<u8> {
+        let dim = self.table.dim();
+        let mut image = Array3::zeros((dim[0], dim[1], 3));
+
+        for (rows, cols, value) in layout.blocks() {
+            let colour = self.colour(rows.clone(), cols.clone(), value);
+            image
+                .slice_mut(s![rows, cols, ..])
+                .genrows_mut()
+                .into_iter()
+                .for_each(|mut pixel| pixel.assign(&aview1(&colour)));
+        }
+
+        image
+    }
+}
+
+impl Fit for ColourFit<'_> {
+    fn fit(&self, rows: Range<usize>, cols: Range<usize>) -> (Value, Distance) {
+        let statistics = self.table.statistics(rows, cols);
+        let mean = statistics.mean();
+
+        let (value, colour) = match self.palette {
+            ColourPalette::Free => (0, mean),
+            ColourPalette::Fixed(_) => {
+                let (index, colour) = self
+                    .coordinates
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        squared_distance(a, &mean)
+                            .partial_cmp(&squared_distance(b, &mean))
+                            .unwrap()
+                    })
+                    .unwrap();
+                (index as Value, *colour)
+            }
+        };
+
+        (value, statistics.squared_error(&colour) * self.recip)
+    }
+}
+
+/// Mean squared distance between the pixels of two images measured in `space`.
+pub(crate) fn distance(space: ColourSpace, a: ArrayView3<u8>, b: ArrayView3<u8>) -> Distance {
+    let (n, m, _) = a.dim();
+    let recip = f64::recip((n * m) as f64);
+
+    a.genrows()
+        .into_iter()
+        .zip(b.genrows())
+        .map(|(a, b)| {
+            squared_distance(
+                &space.coordinates([a[0], a[1], a[2]]),
+                &space.coordinates([b[0], b[1], b[2]]),
+            ) * recip
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::block_matrix::BlockMatrix;
+
+    fn example() -> Array3<u8> {
+        Array3::from_shape_fn((3, 4, 3), |(i, j, k)| match (j < 2, k) {
+            (true, 0) => 200 + i as u8,
+            (false, 2) => 100 + 10 * i as u8,
+            _ => 0,
+        })
+    }
+
+    #[test]
+    fn lab_round_trip() {
+        for &colour in &[[0, 0, 0], [255, 255, 255], [255, 0, 0], [12, 200, 97]] {
+            let lab = ColourSpace::Lab.coordinates(colour);
+            assert_eq!(ColourSpace::Lab.colour(lab), colour);
+        }
+
+        let white = ColourSpace::Lab.coordinates([255, 255, 255]);
+        assert!((white[0] - 100.0).abs() < 1e-3);
+        assert!(white[1].abs() < 1e-3 && white[2].abs() < 1e-3);
+    }
+
+    #[test]
+    fn uniform_palette() {
+        assert_eq!(
+            ColourPalette::uniform(1),
+            Some(ColourPalette::Fixed(vec![[128, 128, 128]]))
+        );
+        match ColourPalette::uniform(6) {
+            Some(ColourPalette::Fixed(colours)) => assert_eq!(colours.len(), 216),
+            palette => panic!("{:?}", palette),
+        }
+        assert_eq!(ColourPalette::uniform(7), None);
+    }
+
+    #[test]
+    fn fitted_distance_matches_rendered_image() {
+        let image = example();
+
+        for &space in &[ColourSpace::Rgb, ColourSpace::Lab] {
+            let table = ColourTable::new(image.view(), space);
+
+            for palette in &[ColourPalette::Free, ColourPalette::uniform(6).unwrap()] {
+                let fit = ColourFit::new(&table, palette);
+                let mut matrix = BlockMatrix::zeros(3, 2, 3, 4);
+                let fitted = matrix.optimise_values(&fit);
+
+                let rendered = fit.render(&matrix);
+                let exact = distance(space, image.view(), rendered.view());
+                let tolerance = match palette {
+                    ColourPalette::Free => 1.0,
+                    ColourPalette::Fixed(_) => 1e-9,
+                };
+                assert!(
+                    (fitted - exact).abs() < tolerance,
+                    "{:?} {:?}: {} {}",
+                    space,
+                    palette,
+                    fitted,
+                    exact
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use super::{Distance, Value, ValueMatrix};
+use ndarray::prelude::*;
+use std::fmt;
+
+/// Per-pixel error the block values are fitted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockError {
+    /// Fitted by the palette entry nearest to the block's mean.
+    Squared,
+    /// Fitted by the palette entry nearest to the block's median.
+    Absolute,
+}
+
+/// Error of an approximation of an image, lower is better.
+pub(crate) trait Metric: fmt::Debug {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance;
+
+    /// The block error `distance` is the mean of, if any, so that the block fits compute it
+    /// without rendering the approximation. Other metrics get squared error fits and only
+    /// score the rendered layouts.
+    fn block_error(&self) -> Option<BlockError> {
+        None
+    }
+
+    /// The number shown to the user for `distance`.
+    fn report(&self, distance: Distance) -> f64 {
+        distance
+    }
+}
+
+fn mean_of<F>(original: &ValueMatrix, approximation: &ValueMatrix, f: F) -> Distance
+where
+    F: Fn(f64) -> f64,
+{
+    let sum: f64 = original
+        .iter()
+        .zip(approximation)
+        .map(|(&a, &b)| f(f64::from(a) - f64::from(b)))
+        .sum();
+
+    sum / original.len() as f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct MeanSquaredError;
+
+impl Metric for MeanSquaredError {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance {
+        mean_of(original, approximation, |d| d * d)
+    }
+
+    fn block_error(&self) -> Option<BlockError> {
+        Some(BlockError::Squared)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct MeanAbsoluteError;
+
+impl Metric for MeanAbsoluteError {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance {
+        mean_of(original, approximation, f64::abs)
+    }
+
+    fn block_error(&self) -> Option<BlockError> {
+        Some(BlockError::Absolute)
+    }
+}
+
+/// Optimises the mean squared error but reports the peak signal-to-noise ratio in dB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct PeakSignalToNoise;
+
+impl Metric for PeakSignalToNoise {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance {
+        MeanSquaredError.distance(original, approximation)
+    }
+
+    fn block_error(&self) -> Option<BlockError> {
+        Some(BlockError::Squared)
+    }
+
+    fn report(&self, distance: Distance) -> f64 {
+        let peak = f64::from(Value::MAX);
+        10.0 * f64::log10(peak * peak / distance)
+    }
+}
+
+/// `1 - SSIM`, with SSIM averaged over all `window × window` squares of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StructuralDissimilarity {
+    pub window: usize,
+}
+
+impl Default for StructuralDissimilarity {
+    fn default() -> Self {
+        Self { window: 8 }
+    }
+}
+
+/// Prefix sums of `f(original, approximation)` over the whole image.
+fn prefix_sums<F>(original: &ValueMatrix, approximation: &ValueMatrix, f: F) -> Array2<f64>
+where
+    F: Fn(f64, f64) -> f64,
+{
+    let (n, m) = original.dim();
+    let mut sums = Array2::zeros((n + 1, m + 1));
+
+    for ((i, j), &a) in original.indexed_iter() {
+        let value = f(f64::from(a), f64::from(approximation[[i, j]]));
+        sums[[i + 1, j + 1]] = value + sums[[i, j + 1]] + sums[[i + 1, j]] - sums[[i, j]];
+    }
+
+    sums
+}
+
+impl Metric for StructuralDissimilarity {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance {
+        let peak = f64::from(Value::MAX);
+        let c1 = (0.01 * peak).powi(2);
+        let c2 = (0.03 * peak).powi(2);
+
+        let (n, m) = original.dim();
+        let (wh, ww) = (self.window.min(n), self.window.min(m));
+        let count = (wh * ww) as f64;
+
+        let sums = [
+            prefix_sums(original, approximation, |a, _| a),
+            prefix_sums(original, approximation, |_, b| b),
+            prefix_sums(original, approximation, |a, _| a * a),
+            prefix_sums(original, approximation, |_, b| b * b),
+            prefix_sums(original, approximation, |a, b| a * b),
+        ];
+
+        let windows = (n - wh + 1) * (m - ww + 1);
+        let mut total = 0.0;
+
+        for i in 0..=n - wh {
+            for j in 0..=m - ww {
+                let mut window = sums.iter().map(|s| {
+                    (s[[i + wh, j + ww]] + s[[i, j]] - s[[i, j + ww]] - s[[i + wh, j]]) / count
+                });
+                let (mean_a, mean_b) = (window.next().unwrap(), window.next().unwrap());
+                let variance_a = window.next().unwrap() - mean_a * mean_a;
+                let variance_b = window.next().unwrap() - mean_b * mean_b;
+                let covariance = window.next().unwrap() - mean_a * mean_b;
+
+                total += ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+                    / ((mean_a * mean_a + mean_b * mean_b + c1) * (variance_a + variance_b + c2));
+            }
+        }
+
+        1.0 - total / windows as f64
+    }
+}
+
+/// Squared error weighted by `1 + strength × gradient`, where the gradient magnitude of the
+/// original is scaled to `[0, 1]`, so that errors on edges cost more than in flat regions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EdgeWeightedError {
+    pub strength: f64,
+}
+
+impl Default for EdgeWeightedError {
+    fn default() -> Self {
+        Self { strength: 4.0 }
+    }
+}
+
+impl EdgeWeightedError {
+    fn weights(&self, original: &ValueMatrix) -> Array2<f64> {
+        let (n, m) = original.dim();
+        let value = |i: usize, j: usize| f64::from(original[[i.min(n - 1), j.min(m - 1)]]);
+        let peak = f64::from(Value::MAX);
+
+        Array2::from_shape_fn((n, m), |(i, j)| {
+            let dx = value(i, j + 1) - value(i, j.saturating_sub(1));
+            let dy = value(i + 1, j) - value(i.saturating_sub(1), j);
+            let gradient = f64::hypot(dx, dy) / (2.0 * std::f64::consts::SQRT_2 * peak);
+            1.0 + self.strength * gradient
+        })
+    }
+}
+
+impl Metric for EdgeWeightedError {
+    fn distance(&self, original: &ValueMatrix, approximation: &ValueMatrix) -> Distance {
+        let weights = self.weights(original);
+
+        let (weighted, total) = original.iter().zip(approximation).zip(&weights).fold(
+            (0.0, 0.0),
+            |(weighted, total), ((&a, &b), &w)| {
+                let d = f64::from(a) - f64::from(b);
+                (weighted + w * d * d, total + w)
+            },
+        );
+
+        weighted / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> ValueMatrix {
+        array![
+            [0, 10, 200, 210, 220],
+            [20, 30, 230, 240, 250],
+            [100, 100, 50, 50, 60],
+        ]
+    }
+
+    fn metrics() -> Vec<Box<dyn Metric>> {
+        vec![
+            Box::new(MeanSquaredError),
+            Box::new(MeanAbsoluteError),
+            Box::new(PeakSignalToNoise),
+            Box::new(StructuralDissimilarity::default()),
+            Box::new(StructuralDissimilarity { window: 2 }),
+            Box::new(EdgeWeightedError::default()),
+        ]
+    }
+
+    #[test]
+    fn identical_images_have_zero_distance() {
+        let values = example();
+        for metric in metrics() {
+            assert!(
+                metric.distance(&values, &values).abs() < 1e-12,
+                "{:?}",
+                metric
+            );
+        }
+    }
+
+    #[test]
+    fn distance_grows_with_error() {
+        let values = example();
+        let slightly = values.mapv(|v| v.saturating_add(5));
+        let heavily = values.mapv(|v| v.saturating_add(40));
+
+        for metric in metrics() {
+            let slight = metric.distance(&values, &slightly);
+            let heavy = metric.distance(&values, &heavily);
+            assert!(
+                0.0 < slight && slight < heavy,
+                "{:?}: {} {}",
+                metric,
+                slight,
+                heavy
+            );
+        }
+    }
+
+    #[test]
+    fn simple_errors() {
+        let values = example();
+        let shifted = values.mapv(|v| v.saturating_add(5));
+
+        assert_eq!(MeanAbsoluteError.distance(&values, &shifted), 5.0);
+        assert_eq!(MeanSquaredError.distance(&values, &shifted), 25.0);
+        assert!((PeakSignalToNoise.report(25.0) - 34.151).abs() < 1e-3);
+    }
+
+    #[test]
+    fn edge_weighted_prefers_errors_in_flat_regions() {
+        let values = array![[0, 0, 0, 255, 255], [0, 0, 0, 255, 255]];
+        let mut flat = values.clone();
+        flat[[0, 0]] = 10;
+        let mut edge = values.clone();
+        edge[[0, 2]] = 10;
+
+        let metric = EdgeWeightedError::default();
+        assert!(metric.distance(&values, &flat) < metric.distance(&values, &edge));
+    }
+}
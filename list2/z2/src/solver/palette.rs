@@ -0,0 +1,164 @@
+use super::Value;
+
+pub(crate) const DEFAULT_VALUES: &[Value] = &[0, 32, 64, 128, 160, 192, 223, 255];
+
+const MAX_KMEANS_ITERATIONS: usize = 100;
+
+/// Intensities the blocks may take, sorted so that neighbouring indices have similar values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Palette {
+    values: Vec<Value>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(DEFAULT_VALUES.to_vec()).unwrap()
+    }
+}
+
+impl Palette {
+    pub fn new(mut values: Vec<Value>) -> Option<Self> {
+        values.sort_unstable();
+        values.dedup();
+
+        if values.is_empty() {
+            None
+        } else {
+            Some(Self { values })
+        }
+    }
+
+    /// `levels` intensities spread evenly over the whole range.
+    pub fn uniform(levels: usize) -> Option<Self> {
+        match levels {
+            0 | 257..=usize::MAX => None,
+            1 => Self::new(vec![128]),
+            _ => Self::new(
+                (0..levels)
+                    .map(|i| (i * 255 + (levels - 1) / 2) / (levels - 1))
+                    .map(|v| v as Value)
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn value(&self, index: Value) -> Value {
+        self.values[index as usize]
+    }
+
+    /// Index of the palette entry closest to `value`.
+    pub fn nearest(&self, value: f64) -> Value {
+        let position = self
+            .values
+            .binary_search_by(|&v| f64::from(v).partial_cmp(&value).unwrap())
+            .unwrap_or_else(|i| i);
+
+        let index = if position == 0 {
+            0
+        } else if position == self.values.len() {
+            position - 1
+        } else {
+            let below = value - f64::from(self.values[position - 1]);
+            let above = f64::from(self.values[position]) - value;
+            if below <= above {
+                position - 1
+            } else {
+                position
+            }
+        };
+
+        index as Value
+    }
+
+    /// Weighted k-means over `(mean, weight)` pairs of block intensities, starting from this palette.
+    pub fn optimise(&self, means: &[(f64, f64)]) -> Self {
+        let mut centers: Vec<_> = self.values.iter().map(|&v| f64::from(v)).collect();
+
+        for _ in 0..MAX_KMEANS_ITERATIONS {
+            let palette = Self::from_centers(&centers);
+            let mut sums = vec![(0.0, 0.0); palette.len()];
+            for &(mean, weight) in means {
+                let sum = &mut sums[palette.nearest(mean) as usize];
+                sum.0 += mean * weight;
+                sum.1 += weight;
+            }
+
+            let next: Vec<_> = sums
+                .iter()
+                .zip(palette.values.iter())
+                .map(|(&(sum, weight), &old)| {
+                    if weight > 0.0 {
+                        sum / weight
+                    } else {
+                        f64::from(old)
+                    }
+                })
+                .collect();
+
+            if Self::from_centers(&next) == palette {
+                break;
+            }
+            centers = next;
+        }
+
+        Self::from_centers(&centers)
+    }
+
+    fn from_centers(centers: &[f64]) -> Self {
+        Self::new(centers.iter().map(|&c| c.round() as Value).collect()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_sorts_and_deduplicates() {
+        let palette = Palette::new(vec![255, 0, 128, 0]).unwrap();
+        assert_eq!(palette.values(), &[0, 128, 255]);
+        assert_eq!(Palette::new(vec![]), None);
+    }
+
+    #[test]
+    fn uniform() {
+        assert_eq!(Palette::uniform(2).unwrap().values(), &[0, 255]);
+        assert_eq!(Palette::uniform(3).unwrap().values(), &[0, 128, 255]);
+        assert_eq!(Palette::uniform(256).unwrap().len(), 256);
+        assert_eq!(Palette::uniform(0), None);
+        assert_eq!(Palette::uniform(257), None);
+    }
+
+    #[test]
+    fn nearest() {
+        let palette = Palette::default();
+        assert_eq!(palette.nearest(-3.0), 0);
+        assert_eq!(palette.nearest(15.9), 0);
+        assert_eq!(palette.nearest(16.1), 1);
+        assert_eq!(palette.nearest(100.0), 3);
+        assert_eq!(palette.nearest(224.0), 6);
+        assert_eq!(palette.nearest(300.0), 7);
+    }
+
+    #[test]
+    fn optimise_finds_clusters() {
+        let means = [
+            (10.0, 1.0),
+            (12.0, 1.0),
+            (100.0, 2.0),
+            (103.0, 1.0),
+            (250.0, 4.0),
+        ];
+        let palette = Palette::uniform(3).unwrap().optimise(&means);
+        assert_eq!(palette.values(), &[11, 101, 250]);
+    }
+}
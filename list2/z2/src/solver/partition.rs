@@ -0,0 +1,450 @@
+use super::palette::Palette;
+use super::{Distance, Fit, Layout, Value, ValueMatrix};
+use ndarray::prelude::*;
+use rand::prelude::*;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rectangle {
+    pub rows: Range<usize>,
+    pub cols: Range<usize>,
+}
+
+impl Rectangle {
+    fn extent(&self, axis: Axis) -> &Range<usize> {
+        match axis {
+            Axis(0) => &self.rows,
+            _ => &self.cols,
+        }
+    }
+
+    fn split(&self, axis: Axis, position: usize) -> [Self; 2] {
+        let (mut first, mut second) = (self.clone(), self.clone());
+        match axis {
+            Axis(0) => {
+                first.rows.end = position;
+                second.rows.start = position;
+            }
+            _ => {
+                first.cols.end = position;
+                second.cols.start = position;
+            }
+        }
+        [first, second]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Leaf(Value),
+    /// Cuts the node's rectangle at the absolute row (column) `position`.
+    Split {
+        axis: Axis,
+        position: usize,
+        children: Box<[Node; 2]>,
+    },
+}
+
+impl Node {
+    /// The `index`-th node in pre-order.
+    fn find_mut(&mut self, index: &mut usize) -> Option<&mut Node> {
+        if *index == 0 {
+            return Some(self);
+        }
+        *index -= 1;
+
+        match self {
+            Node::Leaf(_) => None,
+            Node::Split { children, .. } => {
+                let [first, second] = &mut **children;
+                first
+                    .find_mut(index)
+                    .or_else(move || second.find_mut(index))
+            }
+        }
+    }
+
+    fn visit<'a>(&'a self, rectangle: Rectangle, f: &mut impl FnMut(&'a Node, &Rectangle)) {
+        f(self, &rectangle);
+        if let Node::Split {
+            axis,
+            position,
+            children,
+        } = self
+        {
+            let [first, second] = rectangle.split(*axis, *position);
+            children[0].visit(first, f);
+            children[1].visit(second, f);
+        }
+    }
+
+    fn visit_leaves_mut(
+        &mut self,
+        rectangle: Rectangle,
+        f: &mut impl FnMut(&mut Value, Rectangle),
+    ) {
+        match self {
+            Node::Leaf(value) => f(value, rectangle),
+            Node::Split {
+                axis,
+                position,
+                children,
+            } => {
+                let [first, second] = rectangle.split(*axis, *position);
+                children[0].visit_leaves_mut(first, f);
+                children[1].visit_leaves_mut(second, f);
+            }
+        }
+    }
+
+    /// Positions of all cuts along `axis` in this subtree.
+    fn cuts(&self, along: Axis, cuts: &mut Vec<usize>) {
+        if let Node::Split {
+            axis,
+            position,
+            children,
+        } = self
+        {
+            if *axis == along {
+                cuts.push(*position);
+            }
+            children[0].cuts(along, cuts);
+            children[1].cuts(along, cuts);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PartitionMove {
+    /// Splits the leaf with pre-order index `node`.
+    Split {
+        node: usize,
+        axis: Axis,
+        position: usize,
+    },
+    /// Replaces a split of two leaves with a single leaf.
+    Merge { node: usize },
+    /// Moves the cut of a split to `position`.
+    MoveEdge { node: usize, position: usize },
+}
+
+/// Guillotine partition of the image into rectangles, each filled with one palette value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Partition {
+    root: Node,
+    height: usize,
+    width: usize,
+}
+
+impl Partition {
+    pub fn new(height: usize, width: usize) -> Self {
+        Self {
+            root: Node::Leaf(0),
+            height,
+            width,
+        }
+    }
+
+    fn bounds(&self) -> Rectangle {
+        Rectangle {
+            rows: 0..self.height,
+            cols: 0..self.width,
+        }
+    }
+
+    /// Pre-order list of the nodes with their rectangles.
+    fn nodes(&self) -> Vec<(&Node, Rectangle)> {
+        let mut nodes = Vec::new();
+        self.root.visit(self.bounds(), &mut |node, rectangle| {
+            nodes.push((node, rectangle.clone()))
+        });
+        nodes
+    }
+
+    pub fn leaves(&self) -> Vec<(Rectangle, Value)> {
+        self.nodes()
+            .into_iter()
+            .filter_map(|(node, rectangle)| match node {
+                Node::Leaf(value) => Some((rectangle, *value)),
+                Node::Split { .. } => None,
+            })
+            .collect()
+    }
+
+    /// A random split, merge or edge move keeping every rectangle at least
+    /// `minimal_block_size` in both dimensions.
+    pub fn random_move(
+        &self,
+        minimal_block_size: usize,
+        rng: &mut impl Rng,
+    ) -> Option<PartitionMove> {
+        let nodes = self.nodes();
+
+        match rng.gen_range(0, 3) {
+            0 => {
+                let leaves: Vec<_> = nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (node, _))| matches!(node, Node::Leaf(_)))
+                    .collect();
+                let &(node, (_, rectangle)) = leaves.choose(rng)?;
+                let axis = Axis(rng.gen_range(0, 2));
+                let extent = rectangle.extent(axis);
+
+                if extent.len() < 2 * minimal_block_size {
+                    None
+                } else {
+                    let position = rng.gen_range(
+                        extent.start + minimal_block_size,
+                        extent.end - minimal_block_size + 1,
+                    );
+                    Some(PartitionMove::Split {
+                        node,
+                        axis,
+                        position,
+                    })
+                }
+            }
+            1 => {
+                let mergeable: Vec<_> = nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (node, _))| match node {
+                        Node::Split { children, .. } => {
+                            children.iter().all(|child| matches!(child, Node::Leaf(_)))
+                        }
+                        Node::Leaf(_) => false,
+                    })
+                    .map(|(node, _)| node)
+                    .collect();
+
+                Some(PartitionMove::Merge {
+                    node: *mergeable.choose(rng)?,
+                })
+            }
+            _ => {
+                let splits: Vec<_> = nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (node, _))| matches!(node, Node::Split { .. }))
+                    .collect();
+                let &(node, (split, rectangle)) = splits.choose(rng)?;
+
+                if let Node::Split {
+                    axis,
+                    position,
+                    children,
+                } = split
+                {
+                    let extent = rectangle.extent(*axis);
+                    let (mut first, mut second) = (Vec::new(), Vec::new());
+                    children[0].cuts(*axis, &mut first);
+                    children[1].cuts(*axis, &mut second);
+
+                    let low = first.into_iter().max().unwrap_or(extent.start) + minimal_block_size;
+                    let high = second.into_iter().min().unwrap_or(extent.end) - minimal_block_size;
+                    let new_position = rng.gen_range(low, high + 1);
+
+                    if new_position == *position {
+                        None
+                    } else {
+                        Some(PartitionMove::MoveEdge {
+                            node,
+                            position: new_position,
+                        })
+                    }
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn with_move(&self, partition_move: PartitionMove) -> Self {
+        let mut new = self.clone();
+
+        match partition_move {
+            PartitionMove::Split {
+                mut node,
+                axis,
+                position,
+            } => {
+                let node = new.root.find_mut(&mut node).unwrap();
+                if let Node::Leaf(value) = *node {
+                    *node = Node::Split {
+                        axis,
+                        position,
+                        children: Box::new([Node::Leaf(value), Node::Leaf(value)]),
+                    };
+                }
+            }
+            PartitionMove::Merge { mut node } => {
+                let node = new.root.find_mut(&mut node).unwrap();
+                if let Node::Split { children, .. } = node {
+                    if let Node::Leaf(value) = children[0] {
+                        *node = Node::Leaf(value);
+                    }
+                }
+            }
+            PartitionMove::MoveEdge { mut node, position } => {
+                if let Node::Split {
+                    position: current, ..
+                } = new.root.find_mut(&mut node).unwrap()
+                {
+                    *current = position;
+                }
+            }
+        }
+
+        new
+    }
+}
+
+impl Layout for Partition {
+    fn blocks(&self) -> Vec<(Range<usize>, Range<usize>, Value)> {
+        self.leaves()
+            .into_iter()
+            .map(|(rectangle, value)| (rectangle.rows, rectangle.cols, value))
+            .collect()
+    }
+
+    fn optimise_values(&mut self, fit: &impl Fit) -> Distance {
+        let bounds = self.bounds();
+        let mut distance = 0.0_f64;
+
+        self.root.visit_leaves_mut(bounds, &mut |value, rectangle| {
+            let (fitted, error) = fit.fit(rectangle.rows, rectangle.cols);
+            *value = fitted;
+            distance += error;
+        });
+
+        distance
+    }
+
+    fn to_full_size(&self, palette: &Palette, dim: Ix2) -> ValueMatrix {
+        let mut full_size = ValueMatrix::zeros(dim);
+
+        for (rectangle, value) in self.leaves() {
+            full_size
+                .slice_mut(s![rectangle.rows, rectangle.cols])
+                .fill(palette.value(value));
+        }
+
+        full_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::metric::{MeanSquaredError, Metric};
+    use crate::solver::summed_area::SummedAreaTable;
+    use crate::solver::GrayFit;
+
+    fn mean_squared_error(
+        layout: &impl Layout,
+        palette: &Palette,
+        values: &ValueMatrix,
+    ) -> Distance {
+        MeanSquaredError.distance(values, &layout.to_full_size(palette, values.raw_dim()))
+    }
+
+    fn example() -> ValueMatrix {
+        array![
+            [0, 10, 200, 210, 220],
+            [20, 30, 230, 240, 250],
+            [100, 100, 50, 50, 60],
+        ]
+    }
+
+    fn assert_valid(partition: &Partition, minimal_block_size: usize) {
+        let leaves = partition.leaves();
+        let area: usize = leaves
+            .iter()
+            .map(|(r, _)| r.rows.len() * r.cols.len())
+            .sum();
+
+        assert_eq!(area, partition.height * partition.width);
+        for (rectangle, _) in leaves {
+            assert!(rectangle.rows.len() >= minimal_block_size);
+            assert!(rectangle.cols.len() >= minimal_block_size);
+        }
+    }
+
+    #[test]
+    fn moves() {
+        let partition = Partition::new(3, 5);
+        let split = partition.with_move(PartitionMove::Split {
+            node: 0,
+            axis: Axis(1),
+            position: 2,
+        });
+        let split = split.with_move(PartitionMove::Split {
+            node: 2,
+            axis: Axis(0),
+            position: 2,
+        });
+        assert_eq!(
+            split
+                .leaves()
+                .into_iter()
+                .map(|(r, _)| r)
+                .collect::<Vec<_>>(),
+            vec![
+                Rectangle {
+                    rows: 0..3,
+                    cols: 0..2
+                },
+                Rectangle {
+                    rows: 0..2,
+                    cols: 2..5
+                },
+                Rectangle {
+                    rows: 2..3,
+                    cols: 2..5
+                },
+            ]
+        );
+
+        let moved = split.with_move(PartitionMove::MoveEdge {
+            node: 0,
+            position: 3,
+        });
+        assert_eq!(moved.leaves()[0].0.cols, 0..3);
+
+        let merged = split.with_move(PartitionMove::Merge { node: 2 });
+        assert_eq!(
+            merged,
+            partition.with_move(PartitionMove::Split {
+                node: 0,
+                axis: Axis(1),
+                position: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn random_moves_keep_rectangles_valid() {
+        let values = example();
+        let table = SummedAreaTable::new(&values);
+        let palette = Palette::default();
+        let fit = GrayFit::new(&palette, &table);
+        let rng = &mut thread_rng();
+
+        for &minimal_block_size in &[1, 2] {
+            let mut partition = Partition::new(3, 5);
+
+            for _ in 0..1000 {
+                if let Some(partition_move) = partition.random_move(minimal_block_size, rng) {
+                    partition = partition.with_move(partition_move);
+                }
+                assert_valid(&partition, minimal_block_size);
+
+                let distance = partition.optimise_values(&fit);
+                assert!(
+                    (distance - mean_squared_error(&partition, &palette, &values)).abs() < 1e-9
+                );
+            }
+        }
+    }
+}
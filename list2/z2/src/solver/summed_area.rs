@@ -0,0 +1,99 @@
+use super::Value;
+use ndarray::prelude::*;
+use std::ops::Range;
+
+/// Prefix sums of values and squared values, giving the statistics of any rectangle in O(1).
+#[derive(Debug, Clone)]
+pub(crate) struct SummedAreaTable {
+    sums: Array2<u64>,
+    squares: Array2<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BlockStatistics {
+    pub count: f64,
+    pub sum: f64,
+    pub sum_of_squares: f64,
+}
+
+impl BlockStatistics {
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count
+    }
+
+    /// Sum of squared differences between the block's values and `value`.
+    pub fn squared_error(&self, value: f64) -> f64 {
+        self.sum_of_squares - 2.0 * value * self.sum + self.count * value * value
+    }
+}
+
+impl SummedAreaTable {
+    pub fn new(values: &Array2<Value>) -> Self {
+        let (n, m) = values.dim();
+        let mut sums = Array2::zeros((n + 1, m + 1));
+        let mut squares = Array2::zeros((n + 1, m + 1));
+
+        for ((i, j), &value) in values.indexed_iter() {
+            let value = u64::from(value);
+            sums[[i + 1, j + 1]] = value + sums[[i, j + 1]] + sums[[i + 1, j]] - sums[[i, j]];
+            squares[[i + 1, j + 1]] =
+                value * value + squares[[i, j + 1]] + squares[[i + 1, j]] - squares[[i, j]];
+        }
+
+        Self { sums, squares }
+    }
+
+    pub fn dim(&self) -> Ix2 {
+        let (n, m) = self.sums.dim();
+        Ix2(n - 1, m - 1)
+    }
+
+    fn rectangle(table: &Array2<u64>, rows: &Range<usize>, cols: &Range<usize>) -> u64 {
+        table[[rows.end, cols.end]] + table[[rows.start, cols.start]]
+            - table[[rows.start, cols.end]]
+            - table[[rows.end, cols.start]]
+    }
+
+    pub fn statistics(&self, rows: Range<usize>, cols: Range<usize>) -> BlockStatistics {
+        BlockStatistics {
+            count: (rows.len() * cols.len()) as f64,
+            sum: Self::rectangle(&self.sums, &rows, &cols) as f64,
+            sum_of_squares: Self::rectangle(&self.squares, &rows, &cols) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_match_direct_sums() {
+        let values = array![[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 255]];
+        let table = SummedAreaTable::new(&values);
+
+        for rows in (0..3).flat_map(|start| (start + 1..=3).map(move |end| start..end)) {
+            for cols in (0..4).flat_map(|start| (start + 1..=4).map(move |end| start..end)) {
+                let block = values.slice(s![rows.clone(), cols.clone()]);
+                let statistics = table.statistics(rows.clone(), cols.clone());
+
+                assert_eq!(statistics.count, block.len() as f64);
+                assert_eq!(statistics.sum, block.iter().map(|&v| v as f64).sum::<f64>());
+                assert_eq!(
+                    statistics.sum_of_squares,
+                    block.iter().map(|&v| (v as f64).powi(2)).sum::<f64>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn squared_error() {
+        let values = array![[0, 10], [20, 30]];
+        let statistics = SummedAreaTable::new(&values).statistics(0..2, 0..2);
+
+        assert_eq!(statistics.mean(), 15.0);
+        assert_eq!(statistics.squared_error(15.0), 500.0);
+        assert_eq!(statistics.squared_error(0.0), 1400.0);
+    }
+}
@@ -0,0 +1,37 @@
+use ::z1::population::{self, Config, Solution};
+use ::z1::problem::Problem;
+use std::error::Error;
+use std::io::stdin;
+use std::time::Instant;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let problem = Problem::try_from_read(stdin().lock())?;
+
+    let Solution {
+        specimen,
+        argument,
+        value,
+        generations,
+    } = population::search(
+        &Config::default(),
+        problem.starting_point,
+        problem.fitness(),
+        Instant::now() + problem.time_limit,
+    );
+
+    println!("{}", generations);
+
+    for x in specimen.values.iter() {
+        print!("{:b} ", x);
+    }
+
+    println!();
+
+    for x in argument.iter() {
+        print!("{} ", x);
+    }
+
+    print!("{}", value);
+
+    Ok(())
+}
@@ -0,0 +1,4 @@
+//! Minimisation of Xin-She Yang's function with a genetic algorithm over fixed-point genomes.
+
+pub mod population;
+pub mod problem;
@@ -6,33 +6,44 @@ use std::time::Instant;
 
 pub mod specimen;
 
+/// Best specimen found by [`search`], with the point it encodes and the function value there.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Solution {
     pub specimen: Specimen,
     pub argument: Argument,
     pub value: Value,
+    /// Number of generations the search went through.
+    pub generations: usize,
 }
 
 impl Solution {
-    fn new(specimen: &Specimen, argument: Argument, value: Value) -> Self {
-        Self { specimen: *specimen, argument, value }
+    fn new(specimen: &Specimen, argument: Argument, value: Value, generations: usize) -> Self {
+        Self {
+            specimen: *specimen,
+            argument,
+            value,
+            generations,
+        }
     }
 }
 
 pub type Scalar = f64;
 
+/// Number of dimensions of the domain.
 pub const SIZE: usize = 5;
 
 pub type Argument = [Scalar; SIZE];
 pub type Value = f64;
 
+/// Xin-She Yang's function, the sum of `parameters[i] * |x[i]|^(i + 1)`.
 pub struct XsYang {
     parameters: Argument,
 }
 
 impl XsYang {
+    /// Panics unless every parameter lies in `[0, 1]`.
     pub fn new(parameters: Argument) -> Self {
-        assert!(parameters.iter().all(|&x| x >= 0.0 && x <= 1.0));
+        assert!(parameters.iter().all(|x| (0.0..=1.0).contains(x)));
 
         Self { parameters }
     }
@@ -69,18 +80,37 @@ impl SpecimenWithValue {
     }
 }
 
-const GENERATION_SIZE: usize = 500;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Number of specimens kept between generations.
+    pub generation_size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            generation_size: 500,
+        }
+    }
+}
 
-#[derive(Debug, PartialOrd, PartialEq)]
+#[derive(Debug, PartialEq)]
 struct AssertOrd<T> {
     value: T,
 }
 
 impl<T: PartialEq> Eq for AssertOrd<T> {}
 
+impl<T: PartialOrd> PartialOrd for AssertOrd<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl<T: PartialOrd> Ord for AssertOrd<T> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other)
+        self.value
+            .partial_cmp(&other.value)
             .expect("tried to compare non-comparable values")
     }
 }
@@ -89,8 +119,16 @@ fn assert_ord<T: PartialOrd>(value: T) -> AssertOrd<T> {
     AssertOrd { value }
 }
 
-pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) -> Solution {
-    let mut population = Vec::with_capacity(GENERATION_SIZE * 2);
+/// Minimises `fitness` until `time_limit`, starting from a population of random specimens and
+/// the one encoding `starting_point`.
+pub fn search(
+    config: &Config,
+    starting_point: Argument,
+    fitness: XsYang,
+    time_limit: Instant,
+) -> Solution {
+    let generation_size = config.generation_size.max(1);
+    let mut population = Vec::with_capacity(generation_size * 2);
     population.push(SpecimenWithValue::from_argument(&starting_point, &fitness));
 
     let rng = &mut thread_rng();
@@ -103,7 +141,7 @@ pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) ->
             }
             SpecimenWithValue::new(Specimen::new(values), &fitness)
         })
-        .take(GENERATION_SIZE - 1),
+        .take(generation_size - 1),
     );
 
     let mut probabilities = Vec::with_capacity(population.capacity());
@@ -120,7 +158,7 @@ pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) ->
     let mut iters = 0;
     while Instant::now() < time_limit {
         population.sort_unstable_by_key(|s| assert_ord(s.value));
-        population.truncate(GENERATION_SIZE);
+        population.truncate(generation_size);
 
         probabilities.clear();
         probabilities.extend(population.iter().scan(0.0, |acc, s| {
@@ -131,7 +169,7 @@ pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) ->
         let sum = probabilities.last().unwrap();
         let dist = Uniform::new_inclusive(0.0, sum);
         chosen_pairs.clear();
-        for _ in 0..GENERATION_SIZE / 2 {
+        for _ in 0..generation_size / 2 {
             let s1 = probabilities
                 .binary_search_by_key(&assert_ord(&rng.sample(dist)), assert_ord)
                 .unwrap_or_else(|x| x);
@@ -155,7 +193,6 @@ pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) ->
                 Specimen::recombine_inner(s1, s2, i, j)
             };
             population.push(SpecimenWithValue::new(specimen, &fitness))
-
         }
 
         tmp.clear();
@@ -187,15 +224,52 @@ pub fn search(starting_point: Argument, fitness: XsYang, time_limit: Instant) ->
             }
         }));
 
-        population.extend(tmp.drain(..));
+        population.append(&mut tmp);
 
         iters += 1;
     }
 
-    println!("{}", iters);
     let best = population
         .iter()
         .min_by_key(|&s| assert_ord(&s.value))
         .unwrap();
-    Solution::new(&best.specimen, best.specimen.to_argument(), best.value)
+    Solution::new(
+        &best.specimen,
+        best.specimen.to_argument(),
+        best.value,
+        iters,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn search_improves_on_starting_point() {
+        let parameters = [0.123, 0.543, 0.4567, 0.876, 0.4756];
+        let starting_point = [1.0, 1.0, 1.0, 1.0, 1.0];
+        let config = Config {
+            generation_size: 50,
+        };
+
+        let Solution {
+            specimen,
+            argument,
+            value,
+            generations,
+        } = search(
+            &config,
+            starting_point,
+            XsYang::new(parameters),
+            Instant::now() + Duration::from_millis(100),
+        );
+
+        let fitness = XsYang::new(parameters);
+        assert_eq!(argument, specimen.to_argument());
+        assert_eq!(value, fitness.value(&argument));
+        assert!(generations > 0);
+        assert!(value <= fitness.value(&Specimen::from_argument(&starting_point).to_argument()));
+    }
 }
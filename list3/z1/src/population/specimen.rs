@@ -4,6 +4,8 @@ pub const GENOME_LENGTH: i64 = 20;
 pub const GENOME_MASK: i64 = (1 << GENOME_LENGTH) - 1;
 pub const MAX_GENOME_VALUE: i64 = (1 << GENOME_LENGTH) - 2;
 
+/// Point of the domain encoded as one fixed-point number of `GENOME_LENGTH` bits per
+/// dimension, covering `[-5, 5]`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Specimen {
     pub values: [i64; SIZE],
@@ -23,7 +25,7 @@ impl Specimen {
         Self { values }
     }
 
-    pub fn to_argument(&self) -> Argument {
+    pub fn to_argument(self) -> Argument {
         let mut argument: Argument = [0.0; SIZE];
         argument
             .iter_mut()
@@ -58,9 +60,7 @@ impl Specimen {
         let mut values = s1.values;
         values[i] = s2.values[i];
 
-        Self {
-            values,
-        }
+        Self { values }
     }
 
     pub fn mutate_big(specimen: &Self, i: usize) -> Self {
@@ -69,9 +69,7 @@ impl Specimen {
 
         *value = !*value & GENOME_MASK;
 
-        Self {
-            values,
-        }
+        Self { values }
     }
 
     pub fn mutate_small(specimen: &Self, positions: &[i64]) -> Self {
@@ -0,0 +1,145 @@
+use crate::population::{Argument, Scalar, XsYang, SIZE};
+use std::convert::TryInto;
+use std::fmt;
+use std::io::Read;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub enum ProblemCreationError {
+    InvalidHeader,
+    InvalidParameters,
+    IOError(String),
+}
+
+impl fmt::Display for ProblemCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemCreationError::InvalidHeader => write!(
+                f,
+                "Expected a positive time limit and {} integer coordinates",
+                SIZE
+            ),
+            ProblemCreationError::InvalidParameters => {
+                write!(f, "Expected {} parameters between 0 and 1", SIZE)
+            }
+            ProblemCreationError::IOError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProblemCreationError {}
+
+impl From<std::io::Error> for ProblemCreationError {
+    fn from(err: std::io::Error) -> Self {
+        ProblemCreationError::IOError(err.to_string())
+    }
+}
+
+/// Time limit, starting point and parameters of the function to minimise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub time_limit: Duration,
+    pub starting_point: Argument,
+    pub parameters: Argument,
+}
+
+impl Problem {
+    /// Reads whitespace separated numbers: the time limit in seconds, `SIZE` integer
+    /// coordinates of the starting point and `SIZE` parameters between 0 and 1.
+    pub fn try_from_read<R: Read>(mut reader: R) -> Result<Self, ProblemCreationError> {
+        use ProblemCreationError::*;
+
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let mut input = input.split_ascii_whitespace();
+        let header = input
+            .by_ref()
+            .take(SIZE + 1)
+            .map(str::parse::<i64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InvalidHeader)?;
+
+        let (time, starting_point) = match *header.as_slice() {
+            [time, ref starting_point @ ..] if time > 0 && starting_point.len() == SIZE => {
+                let mut argument = [0.0; SIZE];
+                for (a, &x) in argument.iter_mut().zip(starting_point) {
+                    *a = x as Scalar;
+                }
+                (time, argument)
+            }
+            _ => return Err(InvalidHeader),
+        };
+
+        let parameters: Argument = input
+            .map(str::parse::<Scalar>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InvalidParameters)?
+            .as_slice()
+            .try_into()
+            .map_err(|_| InvalidParameters)?;
+
+        if !parameters.iter().all(|x| (0.0..=1.0).contains(x)) {
+            return Err(InvalidParameters);
+        }
+
+        Ok(Self {
+            time_limit: Duration::from_secs(time as u64),
+            starting_point,
+            parameters,
+        })
+    }
+
+    pub fn fitness(&self) -> XsYang {
+        XsYang::new(self.parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read(input: &str) -> Result<Problem, ProblemCreationError> {
+        Problem::try_from_read(Cursor::new(input))
+    }
+
+    #[test]
+    fn create_ok() {
+        let problem = read("5 1 1 1 1 -1\n0.123 0.543 0.4567 0.876 1").unwrap();
+
+        assert_eq!(problem.time_limit, Duration::from_secs(5));
+        assert_eq!(problem.starting_point, [1.0, 1.0, 1.0, 1.0, -1.0]);
+        assert_eq!(problem.parameters, [0.123, 0.543, 0.4567, 0.876, 1.0]);
+    }
+
+    #[test]
+    fn create_err_header() {
+        use ProblemCreationError::*;
+        assert_eq!(read("").err(), Some(InvalidHeader));
+        assert_eq!(read("0 1 1 1 1 1 0 0 0 0 0").err(), Some(InvalidHeader));
+        assert_eq!(read("a 1 1 1 1 1 0 0 0 0 0").err(), Some(InvalidHeader));
+        assert_eq!(read("1 1 1 1 1 0.5 0 0 0 0 0").err(), Some(InvalidHeader));
+        assert_eq!(read("1 1 1 1").err(), Some(InvalidHeader));
+    }
+
+    #[test]
+    fn create_err_parameters() {
+        use ProblemCreationError::*;
+        assert_eq!(read("1 1 1 1 1 1").err(), Some(InvalidParameters));
+        assert_eq!(read("1 1 1 1 1 1 0 0 0 0").err(), Some(InvalidParameters));
+        assert_eq!(
+            read("1 1 1 1 1 1 0 0 0 0 0 0").err(),
+            Some(InvalidParameters)
+        );
+        assert_eq!(read("1 1 1 1 1 1 0 0 0 0 a").err(), Some(InvalidParameters));
+        assert_eq!(
+            read("1 1 1 1 1 1 0 0 0 0 1.5").err(),
+            Some(InvalidParameters)
+        );
+        assert_eq!(
+            read("1 1 1 1 1 1 0 0 0 -0.1 0").err(),
+            Some(InvalidParameters)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use ::z2::words::anagram::AnagramIndex;
+use ::z2::words::problem::Problem;
+use ::z2::words::scrabble::{self, Board};
+use ::z2::words::word::Word;
+use ::z2::words::word_set::{self, WordSet};
+use ::z2::words::{
+    read_word_list, search, solutions_to_json, words, Config, Dictionary, Fitness, SearchOutcome,
+    Solution,
+};
+use itertools::Itertools;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: z2 [--dictionary <path to a word list, optionally gzipped>] \
+                     [--board <path to a 15x15 Scrabble board>] [--multi] [--json] [--graded] \
+                     [--global <count of the best dictionary words to report>]";
+
+/// Number of the best population members printed with `--json`.
+const JSON_SOLUTIONS: usize = 10;
+
+/// Largest rack for which the multi-word result is checked against the exact optimum.
+const EXACT_RACK_LIMIT: u32 = 20;
+
+struct Options {
+    dictionary: PathBuf,
+    board: Option<PathBuf>,
+    multi: bool,
+    json: bool,
+    fitness: Fitness,
+    global: Option<usize>,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, &'static str> {
+    let mut options = Options {
+        dictionary: PathBuf::from("dict.txt"),
+        board: None,
+        multi: false,
+        json: false,
+        fitness: Fitness::Strict,
+        global: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dictionary" => options.dictionary = args.next().ok_or(USAGE)?.into(),
+            "--board" => options.board = Some(args.next().ok_or(USAGE)?.into()),
+            "--multi" => options.multi = true,
+            "--json" => options.json = true,
+            "--graded" => options.fitness = Fitness::Graded,
+            "--global" => {
+                let count = args.next().ok_or(USAGE)?.parse().map_err(|_| USAGE)?;
+                options.global = Some(count);
+            }
+            _ => return Err(USAGE),
+        }
+    }
+
+    Ok(options)
+}
+
+fn main_interactive() -> Result<(), Box<dyn Error>> {
+    let options = parse_options(std::env::args().skip(1))?;
+    let start = Instant::now();
+    let Problem {
+        time_limit,
+        available_letters,
+        initial,
+    } = Problem::try_from_read(io::stdin().lock())?;
+
+    let acceptable_words = read_word_list(&options.dictionary)?;
+    let mut dictionary = Dictionary::new(words(&acceptable_words), available_letters);
+    dictionary.fitness = options.fitness;
+
+    eprintln!("initialization took {:?}", start.elapsed());
+    eprintln!("dictionary: {:?}", dictionary.available_letters);
+    eprintln!(
+        "dictionary: {} words, {:?}",
+        dictionary.acceptable_words.len(),
+        dictionary
+            .acceptable_words
+            .iter()
+            .map(|s| Word::new(s.into_boxed_slice()).to_string())
+            .take(10)
+            .collect_vec()
+    );
+
+    if let Some(path) = &options.board {
+        let mut board = Board::try_from_read(BufReader::new(File::open(path)?))?;
+
+        match scrabble::best_placement(&board, &dictionary) {
+            Some(placement) => {
+                println!("{}", placement.score);
+                eprintln!(
+                    "{:?} at row {}, column {}: {}, {} tiles",
+                    placement.direction,
+                    placement.row,
+                    placement.col,
+                    placement.word,
+                    placement.tiles
+                );
+                board.place(&placement);
+                eprint!("{}", board);
+            }
+            None => return Err("no legal placement".into()),
+        }
+
+        return Ok(());
+    }
+
+    let index = if options.multi || options.global.is_some() {
+        Some(AnagramIndex::new(&dictionary.acceptable_words))
+    } else {
+        None
+    };
+
+    if let (true, Some(index)) = (options.multi, &index) {
+        let time_limit = start + time_limit;
+        let WordSet { words, value } =
+            word_set::search(index, &dictionary.available_letters, time_limit);
+
+        println!("{}", value);
+        eprintln!("{}", words.iter().join(" "));
+
+        let tiles: u32 = dictionary.available_letters.values().map(|&(n, _)| n).sum();
+        if tiles <= EXACT_RACK_LIMIT {
+            let exact = word_set::best_set(index, &dictionary.available_letters);
+            eprintln!("exact optimum: {}", exact.value);
+        }
+
+        return Ok(());
+    }
+
+    let best = match (&index, options.global) {
+        (Some(index), Some(count)) => index.top_words(&dictionary.available_letters, count),
+        _ => Vec::new(),
+    };
+
+    let config = Config {
+        solutions: if options.json { JSON_SOLUTIONS } else { 1 },
+        ..Config::default()
+    };
+    let SearchOutcome {
+        solutions,
+        generations,
+        valid_offspring,
+    } = search(&config, initial, dictionary, start + time_limit);
+    eprintln!("{:.1}% of offspring valid", 100.0 * valid_offspring);
+
+    if options.json {
+        println!("{}", solutions_to_json(&solutions));
+    } else {
+        println!("{}", generations);
+        if let Some(Solution { word, value }) = solutions.first() {
+            println!("{}", value);
+            eprintln!("{}", word);
+        }
+    }
+    for Solution { word, value } in best {
+        eprintln!("global with value {}: {}", value, word);
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    main_interactive()
+}
@@ -0,0 +1,5 @@
+//! Searching for the most valuable words that can be built from a set of letter tiles: a
+//! genetic algorithm over single words, exact solvers over an anagram index, a multi-word
+//! mode and Scrabble board placement.
+
+pub mod words;
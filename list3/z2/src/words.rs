@@ -1,17 +1,22 @@
-use crate::words::word::Word;
+use crate::words::trie::Trie;
+use crate::words::word::{Letter, Word};
+use flate2::read::GzDecoder;
 use itertools::Itertools;
 use rand::distributions::{Bernoulli, Uniform};
 use rand::prelude::*;
 use rand_distr::Normal;
 use std::borrow::Cow;
-use std::cell::UnsafeCell;
 #[allow(unused_imports)]
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::fmt;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead as _, BufReader, Read as _};
+use std::path::Path;
 use std::time::Instant;
 
 pub type Value = u64;
 
+/// A word with the sum of the values of its letters.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Solution {
     pub word: Word,
@@ -24,29 +29,72 @@ impl Solution {
     }
 }
 
+/// Exact best words by grouping the dictionary into anagrams.
+pub mod anagram;
+/// Reading the letters and initial words of a search.
+pub mod problem;
+/// Highest scoring placement of the letters on a Scrabble board.
+pub mod scrabble;
+pub mod trie;
 pub mod word;
+/// Sets of words sharing the letters, found exactly or by a genetic search.
+pub mod word_set;
+
+/// How the search scores words that are not in the dictionary or do not fit the rack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fitness {
+    /// Invalid words are worthless and get cut down to a valid prefix when repaired.
+    Strict,
+    /// Invalid words are kept and scored by how close their prefixes come to a valid word.
+    Graded,
+}
+
+/// How mutations choose the letters they insert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LetterSampling {
+    /// Uniformly among the letters continuing the word towards some dictionary word, or
+    /// among all letters of the dictionary when none does.
+    Uniform,
+    /// Among the letters left unused on the rack, weighted by value, preferring those
+    /// continuing the word towards some dictionary word.
+    Rack,
+}
 
-#[derive(Debug)]
-pub struct Dictionary<'a> {
-    pub acceptable_words: HashSet<&'a [u8]>,
-    pub available_letters: BTreeMap<u8, (u32, Value)>,
+/// Graded fitness of a valid word per point of its value, leaving room below it for the
+/// invalid words leading to it.
+const GRADE_SCALE: Value = 4;
+
+/// Acceptable words together with the letters they may be built from.
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    pub acceptable_words: Trie,
+    /// How many tiles of each letter there are and what each is worth.
+    pub available_letters: BTreeMap<Letter, (u32, Value)>,
+    pub fitness: Fitness,
 }
 
-impl<'a> Dictionary<'a> {
-    pub fn new<T: IntoIterator<Item = &'a [u8]>>(
+impl Dictionary {
+    pub fn new<'a, T: IntoIterator<Item = &'a str>>(
         acceptable_words: T,
-        available_letters: BTreeMap<u8, (u32, Value)>,
+        available_letters: BTreeMap<Letter, (u32, Value)>,
     ) -> Self {
-        let acceptable_words = acceptable_words.into_iter().collect();
-        // acceptable_words.sort();
+        let mut trie = Trie::new();
+        let mut letters = Vec::new();
+        for word in acceptable_words {
+            letters.clear();
+            letters.extend(word.chars());
+            trie.insert(&letters);
+        }
 
         Self {
-            acceptable_words,
+            acceptable_words: trie,
             available_letters,
+            fitness: Fitness::Strict,
         }
     }
 
-    pub fn slice_value(&self, word: &[u8]) -> Option<Value> {
+    /// Value of `word`, `None` unless it is acceptable and fits the available letters.
+    pub fn slice_value(&self, word: &[Letter]) -> Option<Value> {
         if !self.acceptable_words.contains(word) {
             return None;
         }
@@ -54,89 +102,258 @@ impl<'a> Dictionary<'a> {
         let mut tmp = word.to_owned();
         tmp.sort_unstable();
 
-        // eprintln!("{}", unsafe { std::str::from_utf8_unchecked(&tmp) });
+        multiset_value(&tmp, &self.available_letters)
+    }
+
+    pub fn word_value(&self, word: &Word) -> Option<Value> {
+        self.slice_value(word.as_slice())
+    }
+
+    /// Score guiding the search, `value` being the value of `word`. Under [`Fitness::Graded`]
+    /// an invalid word scores half the value of its best valid prefix plus a quarter of the
+    /// letters it shares with some word, less a point per over-used letter.
+    pub fn fitness(&self, word: &Word, value: Option<Value>) -> Value {
+        match (self.fitness, value) {
+            (Fitness::Strict, value) => value.unwrap_or(0),
+            (Fitness::Graded, Some(value)) => GRADE_SCALE * value,
+            (Fitness::Graded, None) => {
+                let letters = word.as_slice();
+                let word_prefix = self
+                    .acceptable_words
+                    .word_prefix_lens(letters)
+                    .filter_map(|len| self.slice_value(&letters[..len]))
+                    .max()
+                    .unwrap_or(0);
+
+                let valid_prefix_len = self.acceptable_words.valid_prefix_len(letters);
+                let mut unused = self.unused_letters(&[]);
+                let mut shared = 0;
+                let mut overused = 0;
+                for (i, letter) in letters.iter().enumerate() {
+                    match (unused.get_mut(letter), self.available_letters.get(letter)) {
+                        (Some(count), Some(&(_, value))) if *count > 0 => {
+                            *count -= 1;
+                            if i < valid_prefix_len {
+                                shared += value;
+                            }
+                        }
+                        _ => overused += 1,
+                    }
+                }
 
-        let mut value = 0;
-        for (k, g) in &tmp.iter().group_by(|&&x| x) {
-            let &(c, v) = self.available_letters.get(&k)?;
-            let g_count = g.count() as u32;
-            if g_count > c {
-                return None;
+                (GRADE_SCALE / 2 * word_prefix + shared).saturating_sub(GRADE_SCALE * overused)
             }
+        }
+    }
 
-            value += g_count as u64 * v;
+    /// How many of each available letter `word` leaves unused.
+    fn unused_letters(&self, word: &[Letter]) -> BTreeMap<Letter, u32> {
+        let mut unused: BTreeMap<_, _> = self
+            .available_letters
+            .iter()
+            .map(|(&letter, &(count, _))| (letter, count))
+            .collect();
+        for letter in word {
+            if let Some(count) = unused.get_mut(letter) {
+                *count = count.saturating_sub(1);
+            }
         }
+        unused
+    }
 
-        Some(value)
+    /// Drops the letters of `word` that are unavailable or used more times than available,
+    /// keeping the first occurrences.
+    pub fn drop_overused(&self, word: &Word) -> Word {
+        let mut unused = self.unused_letters(&[]);
+        let letters: Vec<_> = word
+            .as_slice()
+            .iter()
+            .copied()
+            .filter(|letter| match unused.get_mut(letter) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .collect();
+
+        Word::new(letters.into_boxed_slice())
     }
 
-    pub fn word_value(&self, word: &Word) -> Option<Value> {
-        self.slice_value(word.as_slice())
+    /// Drops over-used letters from an invalid `word` and, under [`Fitness::Strict`], cuts it
+    /// down to its longest prefix with a value, if there is one.
+    pub fn repair(&self, word: Word) -> Word {
+        if self.word_value(&word).is_some() {
+            return word;
+        }
+
+        let dropped = self.drop_overused(&word);
+        let word = if dropped.as_slice().is_empty() {
+            word
+        } else {
+            dropped
+        };
+        if self.fitness == Fitness::Graded {
+            return word;
+        }
+
+        let bytes = word.as_slice();
+        let len = self
+            .acceptable_words
+            .word_prefix_lens(bytes)
+            .filter(|&len| self.slice_value(&bytes[..len]).is_some())
+            .last();
+
+        match len {
+            Some(len) => Word::mutate_shorten(&word, len, bytes.len() - len),
+            None => word,
+        }
     }
 }
 
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Reads a UTF-8 list of words, one per line, decompressing it first if it is gzipped.
+/// The words are lowercased.
+pub fn read_word_list(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut file = BufReader::new(File::open(path)?);
+    let compressed = file.fill_buf()?.starts_with(&GZIP_MAGIC);
+
+    let mut text = String::new();
+    if compressed {
+        GzDecoder::new(file).read_to_string(&mut text)?;
+    } else {
+        file.read_to_string(&mut text)?;
+    }
+
+    Ok(text.to_lowercase())
+}
+
+/// Non-empty lines of a word list.
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().map(str::trim).filter(|word| !word.is_empty())
+}
+
+/// Value of the letters in `sorted`, `None` if they use more of some letter than available.
+pub fn multiset_value(
+    sorted: &[Letter],
+    available_letters: &BTreeMap<Letter, (u32, Value)>,
+) -> Option<Value> {
+    let mut value = 0;
+    for (k, g) in &sorted.iter().group_by(|&&x| x) {
+        let &(c, v) = available_letters.get(&k)?;
+        let g_count = g.count() as u32;
+        if g_count > c {
+            return None;
+        }
+
+        value += g_count as u64 * v;
+    }
+
+    Some(value)
+}
+
 #[derive(Debug, Clone)]
 struct WordWithValue {
     word: Word,
     value: Option<Value>,
+    fitness: Value,
 }
 
 impl WordWithValue {
-    fn new(word: Word, dictionary: &Dictionary<'_>) -> Self {
+    fn new(word: Word, dictionary: &Dictionary) -> Self {
         let value = dictionary.word_value(&word);
+        let fitness = dictionary.fitness(&word, value);
 
-        Self { word, value }
+        Self {
+            word,
+            value,
+            fitness,
+        }
     }
 }
 
-pub struct PrettyWord<'a> {
-    word: &'a Word,
+/// Parameters of the genetic [`search`] for a single word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Number of specimens kept between generations, raised to the number of initial words.
+    pub generation_size: usize,
+    /// Number of distinct words returned.
+    pub solutions: usize,
+    pub letter_sampling: LetterSampling,
+    /// Stop after this many generations even before the time limit.
+    pub max_generations: Option<usize>,
 }
 
-impl<'a> PrettyWord<'a> {
-    fn new(word: &'a Word) -> Self {
-        Self { word }
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            generation_size: 5000,
+            solutions: 1,
+            letter_sampling: LetterSampling::Rack,
+            max_generations: None,
+        }
     }
 }
 
-impl fmt::Debug for PrettyWord<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", unsafe {
-            std::str::from_utf8_unchecked(self.word.as_slice())
-        })
-    }
+/// Best words of a [`search`] with statistics of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOutcome {
+    pub solutions: Vec<Solution>,
+    pub generations: usize,
+    /// Share of the words produced by crossover and mutation that were valid.
+    pub valid_offspring: f64,
 }
 
-pub struct PrettyWords<'a, T: IntoIterator<Item = &'a Word>> {
-    words: UnsafeCell<Option<T>>,
-}
+/// Cut points for recombining `s1` and `s2`: `s1` is cut inside its longest valid prefix and,
+/// when possible, `s2` at a letter that can continue the kept part of `s1`.
+fn crossover_points(trie: &Trie, s1: &Word, s2: &Word, rng: &mut impl Rng) -> (usize, usize) {
+    let (s1, s2) = (s1.as_slice(), s2.as_slice());
 
-impl<'a, T: IntoIterator<Item = &'a Word>> PrettyWords<'a, T> {
-    fn new(words: T) -> Self {
-        Self {
-            words: UnsafeCell::new(Some(words)),
-        }
-    }
-}
+    let valid = usize::min(trie.valid_prefix_len(s1), s1.len() - 1);
+    let i = rng.gen_range(0, valid + 1);
 
-impl<'a, T: IntoIterator<Item = &'a Word>> fmt::Debug for PrettyWords<'a, T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_list()
-            .entries(
-                unsafe { &mut *self.words.get() }
-                    .take()
-                    .expect("PrettyWords can only be printed once")
-                    .into_iter()
-                    .map(PrettyWord::new),
-            )
-            .finish()
-    }
+    let next: Vec<_> = trie.next_letters(&s1[..i]).collect();
+    let j = (0..s2.len())
+        .filter(|&j| next.binary_search(&s2[j]).is_ok())
+        .choose(rng)
+        .unwrap_or_else(|| rng.gen_range(0, s2.len()));
+
+    (i, j)
 }
 
-const GENERATION_SIZE: usize = 5000;
+/// A random letter among the `unused` ones, weighted by value, preferring those continuing
+/// `prefix` towards some word.
+fn next_letter(
+    dictionary: &Dictionary,
+    prefix: &[Letter],
+    unused: &BTreeMap<Letter, u32>,
+    rng: &mut impl Rng,
+) -> Option<Letter> {
+    let weight =
+        |letter: &Letter| match (unused.get(letter), dictionary.available_letters.get(letter)) {
+            (Some(&count), Some(&(_, value))) if count > 0 => value.max(1),
+            _ => 0,
+        };
 
-pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instant) -> Solution {
-    let generation_size = usize::max(GENERATION_SIZE, initial.len());
+    let continuing: Vec<_> = dictionary.acceptable_words.next_letters(prefix).collect();
+    let letter = continuing.choose_weighted(rng, weight).ok().copied();
+    letter.or_else(|| {
+        let letters: Vec<_> = unused.keys().copied().collect();
+        letters.choose_weighted(rng, weight).ok().copied()
+    })
+}
+
+/// Evolves the `initial` words until `time_limit` and returns up to `config.solutions`
+/// distinct words of the final population, best first, invalid words counting as worth 0.
+pub fn search(
+    config: &Config,
+    initial: Vec<Word>,
+    dictionary: Dictionary,
+    time_limit: Instant,
+) -> SearchOutcome {
+    let generation_size = usize::max(config.generation_size, initial.len()).max(1);
     let top_specimens = generation_size / 10;
     let mut population = Vec::with_capacity(generation_size * 2);
     population.extend(
@@ -145,10 +362,7 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
             .map(|word| WordWithValue::new(word, &dictionary)),
     );
 
-    eprintln!(
-        "{:?}",
-        PrettyWords::new(population.iter().map(|p| &p.word))
-    );
+    eprintln!("{}", population.iter().map(|p| &p.word).join(" "));
 
     let rng = &mut thread_rng();
 
@@ -157,23 +371,39 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
     let mut tmp = Vec::with_capacity(population.capacity());
     let mut mutations = Vec::with_capacity(64);
 
-    let letter_dist = Uniform::from(b'a'..=b'z');
     let lengthen_dist = Normal::new(1.0f64, 4.0).unwrap();
     let shorten_dist = Normal::new(1.0f64, 2.0).unwrap();
     // let recombination_choice_dist = Bernoulli::from_ratio(1, 3).unwrap();
     let mutation_choice_dist = Bernoulli::from_ratio(1, 10).unwrap();
     let mutation_probability_dist = Bernoulli::from_ratio(1, 20).unwrap();
 
+    let alphabet: Vec<_> = match config.letter_sampling {
+        LetterSampling::Uniform => dictionary.acceptable_words.alphabet().into_iter().collect(),
+        LetterSampling::Rack => Vec::new(),
+    };
+    let sample_letter =
+        |prefix: &[Letter], unused: &BTreeMap<Letter, u32>, rng: &mut ThreadRng| match config
+            .letter_sampling
+        {
+            LetterSampling::Uniform => dictionary
+                .acceptable_words
+                .next_letters(prefix)
+                .choose(rng)
+                .or_else(|| alphabet.choose(rng).copied()),
+            LetterSampling::Rack => next_letter(&dictionary, prefix, unused, rng),
+        };
+
     let mut iters = 0;
-    while Instant::now() < time_limit {
+    let (mut offspring, mut valid_offspring) = (0usize, 0usize);
+    while Instant::now() < time_limit && iters < config.max_generations.unwrap_or(usize::MAX) {
         population.sort_unstable_by(|s1, s2| {
             Ord::cmp(
-                &(std::cmp::Reverse(s1.value.unwrap_or(0)), s1.word.as_slice()),
-                &(std::cmp::Reverse(s2.value.unwrap_or(0)), s2.word.as_slice()),
+                &(std::cmp::Reverse(s1.fitness), s1.word.as_slice()),
+                &(std::cmp::Reverse(s2.fitness), s2.word.as_slice()),
             )
         });
         population.dedup_by(|s1, s2| s1.word == s2.word);
-        let last_acceptable = population.iter().find_position(|s| s.value.is_none());
+        let last_acceptable = population.iter().find_position(|s| s.fitness == 0);
         let last_good =
             last_acceptable.map_or(top_specimens, |(pos, _)| usize::min(pos, top_specimens));
         if let Some(p) = population.get_mut(last_good..) {
@@ -182,15 +412,15 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
 
         if iters % 100 == 10 {
             eprintln!(
-                "{:?}",
-                PrettyWords::new(population.iter().map(|p| &p.word).take(last_good))
+                "{}",
+                population.iter().map(|p| &p.word).take(last_good).join(" ")
             );
         }
         population.truncate(generation_size);
 
         probabilities.clear();
         probabilities.extend(population.iter().scan(0, |acc, s| {
-            *acc += s.value.unwrap_or(1);
+            *acc += s.fitness.max(1);
             Some(*acc)
         }));
 
@@ -211,13 +441,16 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
             let s1 = &population[s1].word;
             let s2 = &population[s2].word;
 
-            let i = rng.gen_range(0, s1.as_slice().len());
-            let j = rng.gen_range(0, s2.as_slice().len());
-
-            let word = Word::recombine(s1, s2, i, j);
+            let (i, j) = crossover_points(&dictionary.acceptable_words, s1, s2, rng);
+            let word = dictionary.repair(Word::recombine(s1, s2, i, j));
 
             population.push(WordWithValue::new(word, &dictionary))
         }
+        offspring += chosen_pairs.len();
+        valid_offspring += population[population.len() - chosen_pairs.len()..]
+            .iter()
+            .filter(|s| s.value.is_some())
+            .count();
 
         tmp.clear();
 
@@ -245,19 +478,46 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
             let (s, mutated) = if rng.sample(mutation_probability_dist) {
                 let len = f64::ceil(rng.sample(lengthen_dist).abs()) as usize;
                 let mut buf = Vec::with_capacity(len + s.as_slice().len());
+                buf.extend_from_slice(s.as_slice());
+                let mut unused = dictionary.unused_letters(&buf);
+
+                for _ in 0..len {
+                    match sample_letter(&buf, &unused, rng) {
+                        Some(letter) => {
+                            buf.push(letter);
+                            if let Some(count) = unused.get_mut(&letter) {
+                                *count = count.saturating_sub(1);
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                let buf = buf.split_off(s.as_slice().len());
 
-                buf.extend(std::iter::repeat_with(|| rng.sample(letter_dist)).take(len));
-
-                (Cow::Owned(Word::mutate_lengthen(&s, buf)), true)
+                if buf.is_empty() {
+                    (s, mutated)
+                } else {
+                    (Cow::Owned(Word::mutate_lengthen(&s, buf)), true)
+                }
             } else {
                 (s, mutated)
             };
 
             let (s, mutated) = if !rng.sample(mutation_choice_dist) {
                 mutations.clear();
-                for (i, _) in s.as_slice().iter().enumerate() {
+                let mut unused = dictionary.unused_letters(s.as_slice());
+                for (i, old) in s.as_slice().iter().enumerate() {
                     if rng.sample(mutation_probability_dist) {
-                        mutations.push((i, rng.sample(letter_dist)));
+                        if let Some(count) = unused.get_mut(old) {
+                            *count += 1;
+                        }
+                        let prefix = &s.as_slice()[..i];
+                        if let Some(letter) = sample_letter(prefix, &unused, rng) {
+                            if let Some(count) = unused.get_mut(&letter) {
+                                *count = count.saturating_sub(1);
+                            }
+                            mutations.push((i, letter));
+                        }
                     }
                 }
                 if !mutations.is_empty() {
@@ -273,7 +533,8 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
             };
 
             if mutated {
-                Some(WordWithValue::new(Cow::into_owned(s), &dictionary))
+                let word = dictionary.repair(Cow::into_owned(s));
+                Some(WordWithValue::new(word, &dictionary))
             } else {
                 None
             }
@@ -281,7 +542,9 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
         tmp.extend(population.iter().filter_map(|s| {
             let mut result = do_mutation(s, rng);
             loop {
-                if !rng.sample(mutation_probability_dist) { break; }
+                if !rng.sample(mutation_probability_dist) {
+                    break;
+                }
                 if let Some(s) = result {
                     result = do_mutation(&s, rng);
                 } else {
@@ -291,34 +554,339 @@ pub fn search(initial: Vec<Word>, dictionary: Dictionary<'_>, time_limit: Instan
             result
         }));
 
-        population.extend(tmp.drain(..));
+        offspring += tmp.len();
+        valid_offspring += tmp.iter().filter(|s| s.value.is_some()).count();
+        population.append(&mut tmp);
 
         iters += 1;
     }
 
-    println!("{}", iters);
-    let best = population.iter().max_by_key(|&s| s.value).unwrap();
-    let best_global = dictionary
-        .acceptable_words
-        .iter()
-        .max_by_key(|w| dictionary.slice_value(w).unwrap_or(0))
-        .unwrap();
-
-    let best_value = dictionary.slice_value(best_global).unwrap_or(0);
-    for i in (0..=best_value).rev().take(5) {
-        let specimens = dictionary.acceptable_words
+    population.sort_unstable_by(|s1, s2| {
+        Ord::cmp(
+            &(std::cmp::Reverse(s1.value.unwrap_or(0)), s1.word.as_slice()),
+            &(std::cmp::Reverse(s2.value.unwrap_or(0)), s2.word.as_slice()),
+        )
+    });
+    population.dedup_by(|s1, s2| s1.word == s2.word);
+
+    SearchOutcome {
+        solutions: population
+            .into_iter()
+            .take(config.solutions)
+            .map(|s| Solution::new(s.word, s.value.unwrap_or(0)))
+            .collect(),
+        generations: iters,
+        valid_offspring: valid_offspring as f64 / offspring.max(1) as f64,
+    }
+}
+
+/// `solutions` as a JSON array of objects with `word` and `value` fields.
+pub fn solutions_to_json(solutions: &[Solution]) -> String {
+    let mut json = String::from("[");
+
+    for (i, Solution { word, value }) in solutions.iter().enumerate() {
+        if i > 0 {
+            json.push_str(", ");
+        }
+        json.push_str("{\"word\": \"");
+        for letter in word.as_slice() {
+            match letter {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                c if c.is_control() => write!(json, "\\u{:04x}", *c as u32).unwrap(),
+                &c => json.push(c),
+            }
+        }
+        write!(json, "\", \"value\": {}}}", value).unwrap();
+    }
+
+    json.push(']');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::anagram::AnagramIndex;
+    use super::*;
+    use std::cmp::Reverse;
+    use std::time::Duration;
+
+    /// Letter counts and values of `test1.txt`.
+    pub(super) fn available_letters() -> BTreeMap<Letter, (u32, Value)> {
+        let mut letters = BTreeMap::new();
+        for (c, &v) in "auneapbsthd"
+            .chars()
+            .zip(&[2, 2, 1, 1, 2, 1, 4, 3, 1, 1, 5])
+        {
+            letters.entry(c).or_insert((0, v)).0 += 1;
+        }
+        letters
+    }
+
+    pub(super) fn dictionary() -> Dictionary {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/dict.txt");
+        let text = read_word_list(path).unwrap();
+
+        Dictionary::new(words(&text), available_letters())
+    }
+
+    #[test]
+    fn top_words_match_brute_force() {
+        let words = ["bean", "abed", "bead", "stead", "daubs", "aaa", "x"];
+        let dictionary = Dictionary::new(words.iter().copied(), available_letters());
+        let index = AnagramIndex::new(&dictionary.acceptable_words);
+
+        let mut expected: Vec<_> = words
             .iter()
-            .map(|w| (w, dictionary.slice_value(w).unwrap_or(0)))
-            .filter(|&(_, v)| v == i);
-        eprint!("global with value {}: ", i);
-        for (specimen, _) in specimens {
-            eprint!(
-                "{} ",
-                unsafe { std::str::from_utf8_unchecked(specimen) },
-            );
+            .map(|&w| Word::from(w))
+            .filter_map(|w| Some((Reverse(dictionary.word_value(&w)?), w)))
+            .collect();
+        expected.sort_unstable();
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(Reverse(value), w)| Solution::new(w, value))
+            .collect();
+
+        assert_eq!(
+            index.top_words(&dictionary.available_letters, 100),
+            expected
+        );
+        assert_eq!(
+            index.top_words(&dictionary.available_letters, 2),
+            expected[..2]
+        );
+        assert_eq!(expected[0].word, Word::from("daubs"));
+        assert_eq!(expected[0].value, 16);
+    }
+
+    #[test]
+    fn top_words_match_brute_force_on_dictionary() {
+        let dictionary = dictionary();
+        let index = AnagramIndex::new(&dictionary.acceptable_words);
+
+        let mut expected: Vec<_> = dictionary
+            .acceptable_words
+            .iter()
+            .map(|w| Word::new(w.into_boxed_slice()))
+            .filter_map(|w| Some((Reverse(dictionary.word_value(&w)?), w)))
+            .collect();
+        expected.sort_unstable();
+        let expected: Vec<_> = expected
+            .into_iter()
+            .take(20)
+            .map(|(Reverse(value), w)| Solution::new(w, value))
+            .collect();
+
+        assert_eq!(index.top_words(&dictionary.available_letters, 20), expected);
+        assert_eq!(expected[0].value, 21);
+    }
+
+    fn run_search(dictionary: &Dictionary, time: Duration, count: usize) -> Vec<Solution> {
+        let initial = ["bean", "stead", "daubs"]
+            .iter()
+            .map(|&w| Word::from(w))
+            .collect();
+
+        let config = Config {
+            solutions: count,
+            ..Config::default()
+        };
+        search(&config, initial, dictionary.clone(), Instant::now() + time).solutions
+    }
+
+    fn optimum(dictionary: &Dictionary) -> Value {
+        AnagramIndex::new(&dictionary.acceptable_words).top_words(&dictionary.available_letters, 1)
+            [0]
+        .value
+    }
+
+    #[test]
+    fn polish_letters() {
+        let mut letters = BTreeMap::new();
+        for (c, v) in "żółwa".chars().zip(1..) {
+            letters.insert(c, (1, v));
+        }
+        let dictionary = Dictionary::new(words("żółw\nżal\r\nłów\n\nwół\n"), letters);
+
+        assert_eq!(dictionary.acceptable_words.len(), 4);
+        assert_eq!(dictionary.word_value(&Word::from("żółw")), Some(10));
+        assert_eq!(dictionary.word_value(&Word::from("łów")), Some(9));
+        assert_eq!(dictionary.word_value(&Word::from("wół")), Some(9));
+        assert_eq!(dictionary.word_value(&Word::from("wółw")), None);
+        assert_eq!(dictionary.word_value(&Word::from("żal")), None);
+    }
+
+    #[test]
+    fn read_gzipped_word_list() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write as _;
+
+        let path = std::env::temp_dir().join(format!("z2-words-{}.gz", std::process::id()));
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all("Żółw\nŁÓW\n".as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let text = read_word_list(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            words(&text.unwrap()).collect::<Vec<_>>(),
+            vec!["żółw", "łów"]
+        );
+    }
+
+    #[test]
+    fn search_returns_valid_word() {
+        let dictionary = dictionary();
+        assert_eq!(optimum(&dictionary), 21);
+
+        let solutions = run_search(&dictionary, Duration::from_millis(300), 5);
+        assert_eq!(solutions.len(), 5);
+        assert_eq!(
+            dictionary.word_value(&solutions[0].word),
+            Some(solutions[0].value)
+        );
+        assert!(solutions[0].value <= 21);
+
+        for (s1, s2) in solutions.iter().tuple_windows() {
+            assert!(s1.value >= s2.value);
+            assert_ne!(s1.word, s2.word);
+        }
+        for Solution { word, value } in &solutions {
+            assert_eq!(dictionary.word_value(word).unwrap_or(0), *value);
         }
-        eprintln!()
     }
 
-    Solution::new(best.word.clone(), best.value.unwrap_or(0))
+    #[test]
+    fn repair_drops_overused_letters() {
+        let dictionary = dictionary();
+
+        let word = dictionary.drop_overused(&Word::from("bbeaxnnaa"));
+        assert_eq!(word, Word::from("beana"));
+        assert_eq!(
+            dictionary.drop_overused(&Word::from("xyz")),
+            Word::default()
+        );
+
+        let word = dictionary.repair(Word::from("bbeaxns"));
+        assert_eq!(word, Word::from("beans"));
+        assert_eq!(dictionary.word_value(&word), Some(11));
+        assert_eq!(dictionary.repair(Word::from("xyz")), Word::from("xyz"));
+    }
+
+    #[test]
+    fn next_letter_uses_unused_letters() {
+        let dictionary = dictionary();
+        let rng = &mut thread_rng();
+        let prefix = Word::from("bea");
+        let unused = dictionary.unused_letters(prefix.as_slice());
+        assert_eq!(unused[&'a'], 1);
+        assert_eq!(unused[&'b'], 0);
+
+        for _ in 0..100 {
+            let letter = next_letter(&dictionary, prefix.as_slice(), &unused, rng).unwrap();
+            assert!(unused[&letter] > 0);
+            assert!(dictionary
+                .acceptable_words
+                .next_letters(prefix.as_slice())
+                .any(|l| l == letter));
+        }
+
+        let unused = dictionary.unused_letters(&"auneapbsthd".chars().collect::<Vec<_>>());
+        assert_eq!(next_letter(&dictionary, &[], &unused, rng), None);
+    }
+
+    #[test]
+    fn graded_fitness() {
+        let mut dictionary = dictionary();
+        let fitness = |dictionary: &Dictionary, word: &str| {
+            let word = Word::from(word);
+            dictionary.fitness(&word, dictionary.word_value(&word))
+        };
+
+        assert_eq!(fitness(&dictionary, "bean"), 8);
+        assert_eq!(fitness(&dictionary, "beanx"), 0);
+
+        dictionary.fitness = Fitness::Graded;
+        assert_eq!(fitness(&dictionary, "bean"), GRADE_SCALE * 8);
+        assert_eq!(
+            fitness(&dictionary, "beanx"),
+            GRADE_SCALE / 2 * 8 + 8 - GRADE_SCALE
+        );
+        assert!(fitness(&dictionary, "beanx") < fitness(&dictionary, "bean"));
+        assert!(fitness(&dictionary, "beanxx") < fitness(&dictionary, "beanx"));
+        assert!(fitness(&dictionary, "unab") < fitness(&dictionary, "unabashe"));
+        assert!(fitness(&dictionary, "unabashe") < fitness(&dictionary, "unabashed"));
+        assert_eq!(fitness(&dictionary, "xyz"), 0);
+
+        let word = dictionary.repair(Word::from("unabbashe"));
+        assert_eq!(word, Word::from("unabashe"));
+    }
+
+    #[test]
+    fn rack_sampling_produces_more_valid_offspring() {
+        const GENERATIONS: usize = 10;
+        let dictionary = dictionary();
+        let valid_offspring = |letter_sampling| {
+            let config = Config {
+                letter_sampling,
+                max_generations: Some(GENERATIONS),
+                ..Config::default()
+            };
+            let initial = vec![Word::from("bean")];
+            let time_limit = Instant::now() + Duration::from_secs(600);
+            let outcome = search(&config, initial, dictionary.clone(), time_limit);
+            assert_eq!(outcome.generations, GENERATIONS);
+            outcome.valid_offspring
+        };
+
+        let uniform = valid_offspring(LetterSampling::Uniform);
+        let rack = valid_offspring(LetterSampling::Rack);
+        assert!(rack > uniform, "{} <= {}", rack, uniform);
+    }
+
+    #[test]
+    fn graded_search_returns_valid_word() {
+        let mut dictionary = dictionary();
+        dictionary.fitness = Fitness::Graded;
+
+        let solutions = run_search(&dictionary, Duration::from_millis(300), 1);
+        let Solution { word, value } = &solutions[0];
+        assert_eq!(dictionary.word_value(word), Some(*value));
+    }
+
+    #[test]
+    fn solutions_as_json() {
+        let solutions = [
+            Solution::new(Word::from("żółw"), 10),
+            Solution::new(Word::from("a\"b\\c\n"), 0),
+        ];
+
+        assert_eq!(solutions_to_json(&[]), "[]");
+        assert_eq!(
+            solutions_to_json(&solutions),
+            r#"[{"word": "żółw", "value": 10}, {"word": "a\"b\\c\u000a", "value": 0}]"#
+        );
+    }
+
+    /// Reports how often the search reaches the optimum, run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn search_optimum_rate() {
+        let dictionary = dictionary();
+        let optimum = optimum(&dictionary);
+
+        let runs = 10;
+        let values: Vec<_> = (0..runs)
+            .map(|_| run_search(&dictionary, Duration::from_secs(2), 1)[0].value)
+            .collect();
+        let found = values.iter().filter(|&&v| v == optimum).count();
+
+        eprintln!(
+            "search found the optimum {} in {} of {} runs: {:?}",
+            optimum, found, runs, values
+        );
+        assert!(values.iter().all(|&v| v <= optimum));
+    }
 }
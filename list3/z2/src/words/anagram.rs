@@ -0,0 +1,110 @@
+use crate::words::trie::Trie;
+use crate::words::word::{Letter, Word};
+use crate::words::{Solution, Value};
+use std::collections::{BTreeMap, HashMap};
+
+type Group = (Box<[Letter]>, Vec<Word>);
+
+/// Words grouped by their sorted letters, all words of a group having the same value.
+#[derive(Debug, Clone)]
+pub struct AnagramIndex {
+    /// Sorted by signature, so groups sharing a prefix are contiguous.
+    groups: Vec<Group>,
+}
+
+/// Adds the groups of `groups`, whose signatures share their first `depth` letters worth
+/// `value`, that fit in the `remaining` counts of the `rack` letters to `fitting`. Letters
+/// missing from the rack skip every group continuing with them at once.
+fn collect_fitting<'a>(
+    mut groups: &'a [Group],
+    depth: usize,
+    value: Value,
+    rack: &[(Letter, Value)],
+    remaining: &mut [u32],
+    fitting: &mut Vec<(&'a [Letter], &'a [Word], Value)>,
+) {
+    if let Some(((signature, words), rest)) = groups.split_first() {
+        if signature.len() == depth {
+            fitting.push((signature, words, value));
+            groups = rest;
+        }
+    }
+
+    while let Some((signature, _)) = groups.first() {
+        let letter = signature[depth];
+        let (continuing, rest) =
+            groups.split_at(groups.partition_point(|(s, _)| s[depth] <= letter));
+
+        if let Ok(i) = rack.binary_search_by_key(&letter, |&(l, _)| l) {
+            if remaining[i] > 0 {
+                remaining[i] -= 1;
+                let value = value + rack[i].1;
+                collect_fitting(continuing, depth + 1, value, rack, remaining, fitting);
+                remaining[i] += 1;
+            }
+        }
+
+        groups = rest;
+    }
+}
+
+impl AnagramIndex {
+    pub fn new(words: &Trie) -> Self {
+        let mut groups = HashMap::<_, Vec<_>>::new();
+
+        for word in words.iter() {
+            let mut signature = word.clone().into_boxed_slice();
+            signature.sort_unstable();
+            groups
+                .entry(signature)
+                .or_default()
+                .push(Word::new(word.into_boxed_slice()));
+        }
+
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_unstable();
+
+        Self { groups }
+    }
+
+    /// Groups of anagrams that can be built from `available_letters`, with their sorted
+    /// letters and value.
+    pub fn fitting<'a>(
+        &'a self,
+        available_letters: &BTreeMap<Letter, (u32, Value)>,
+    ) -> impl Iterator<Item = (&'a [Letter], &'a [Word], Value)> + 'a {
+        let rack: Vec<_> = available_letters
+            .iter()
+            .map(|(&letter, &(_, value))| (letter, value))
+            .collect();
+        let mut remaining: Vec<_> = available_letters
+            .values()
+            .map(|&(count, _)| count)
+            .collect();
+
+        let mut fitting = Vec::new();
+        collect_fitting(&self.groups, 0, 0, &rack, &mut remaining, &mut fitting);
+        fitting.into_iter()
+    }
+
+    /// The `k` most valuable words that can be built from `available_letters`, best first,
+    /// ties broken alphabetically.
+    pub fn top_words(
+        &self,
+        available_letters: &BTreeMap<Letter, (u32, Value)>,
+        k: usize,
+    ) -> Vec<Solution> {
+        let mut solutions: Vec<_> = self
+            .fitting(available_letters)
+            .flat_map(|(_, words, value)| words.iter().map(move |word| (value, word)))
+            .collect();
+
+        solutions.sort_unstable_by(|(v1, w1), (v2, w2)| v2.cmp(v1).then_with(|| w1.cmp(w2)));
+
+        solutions
+            .into_iter()
+            .take(k)
+            .map(|(value, word)| Solution::new(word.clone(), value))
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+use crate::words::word::{Letter, Word};
+use crate::words::{multiset_value, Value};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::BufRead;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub enum ProblemCreationError {
+    InvalidHeader,
+    InvalidLetterLine,
+    InvalidWord,
+    NotEnoughLines,
+    TooManyLines,
+    IOError(String),
+}
+
+impl fmt::Display for ProblemCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemCreationError::InvalidHeader => write!(f, "Invalid header (first line)"),
+            ProblemCreationError::InvalidLetterLine => {
+                write!(f, "Letter lines must have a single letter and its value")
+            }
+            ProblemCreationError::InvalidWord => {
+                write!(f, "Initial words must be built from the available letters")
+            }
+            ProblemCreationError::NotEnoughLines => write!(f, "Not enough lines"),
+            ProblemCreationError::TooManyLines => write!(f, "Too many lines"),
+            ProblemCreationError::IOError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProblemCreationError {}
+
+impl From<std::io::Error> for ProblemCreationError {
+    fn from(err: std::io::Error) -> Self {
+        ProblemCreationError::IOError(err.to_string())
+    }
+}
+
+/// Time limit, letters to build words from and initial population of the word search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub time_limit: Duration,
+    pub available_letters: BTreeMap<Letter, (u32, Value)>,
+    pub initial: Vec<Word>,
+}
+
+impl Problem {
+    /// Reads a header `time n s`, `n` lines with a letter and its value, one line per tile,
+    /// and `s` initial words built from those tiles.
+    pub fn try_from_read<R: BufRead>(reader: R) -> Result<Self, ProblemCreationError> {
+        use ProblemCreationError::*;
+
+        let mut lines = reader.lines();
+        let header = lines
+            .next()
+            .ok_or(NotEnoughLines)??
+            .split_ascii_whitespace()
+            .map(str::parse::<usize>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InvalidHeader)?;
+
+        let (time, n, s) = match *header.as_slice() {
+            [time, n, s] if time > 0 && s > 0 => (time, n, s),
+            _ => return Err(InvalidHeader),
+        };
+
+        let mut available_letters = BTreeMap::new();
+        for _ in 0..n {
+            let line = lines.next().ok_or(NotEnoughLines)??;
+            let (letter, value) = match *line.split_ascii_whitespace().collect_vec() {
+                [letter, value] => (letter, value),
+                _ => return Err(InvalidLetterLine),
+            };
+
+            let letter = match *letter.chars().collect_vec() {
+                [c] if c.is_alphabetic() => c.to_lowercase().next().unwrap_or(c),
+                _ => return Err(InvalidLetterLine),
+            };
+            let value = value.parse::<Value>().map_err(|_| InvalidLetterLine)?;
+
+            let (count, first_value) = available_letters.entry(letter).or_insert((0, value));
+            if *first_value != value {
+                return Err(InvalidLetterLine);
+            }
+            *count += 1;
+        }
+
+        let mut initial = Vec::with_capacity(s);
+        for _ in 0..s {
+            let line = lines.next().ok_or(NotEnoughLines)??.to_lowercase();
+            let word = line.trim();
+
+            let mut sorted = word.chars().collect_vec();
+            sorted.sort_unstable();
+            if word.is_empty() || multiset_value(&sorted, &available_letters).is_none() {
+                return Err(InvalidWord);
+            }
+
+            initial.push(Word::from(word));
+        }
+
+        for line in lines {
+            if !line?.trim().is_empty() {
+                return Err(TooManyLines);
+            }
+        }
+
+        Ok(Self {
+            time_limit: Duration::from_secs(time as u64),
+            available_letters,
+            initial,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read(input: &str) -> Result<Problem, ProblemCreationError> {
+        Problem::try_from_read(Cursor::new(input))
+    }
+
+    #[test]
+    fn create_ok() {
+        let problem = read("3 4 1\na 2\nB 4\nż 1\na 2\nbaża\n\n").unwrap();
+
+        assert_eq!(problem.time_limit, Duration::from_secs(3));
+        assert_eq!(
+            problem.available_letters.into_iter().collect_vec(),
+            vec![('a', (2, 2)), ('b', (1, 4)), ('ż', (1, 1))]
+        );
+        assert_eq!(problem.initial, vec![Word::from("baża")]);
+    }
+
+    #[test]
+    fn create_ok_test_inputs() -> Result<(), ProblemCreationError> {
+        for input in &[
+            include_str!("../../test1.txt"),
+            include_str!("../../test2.txt"),
+        ] {
+            read(input)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn create_err_header() {
+        use ProblemCreationError::*;
+        assert_eq!(read("").err(), Some(NotEnoughLines));
+        assert_eq!(read("a 1 1\na 1\na").err(), Some(InvalidHeader));
+        assert_eq!(read("1 a 1\na 1\na").err(), Some(InvalidHeader));
+        assert_eq!(read("0 1 1\na 1\na").err(), Some(InvalidHeader));
+        assert_eq!(read("1 1 0\na 1").err(), Some(InvalidHeader));
+        assert_eq!(read("1 1\na 1\na").err(), Some(InvalidHeader));
+        assert_eq!(read("1 1 1 1\na 1\na").err(), Some(InvalidHeader));
+    }
+
+    #[test]
+    fn create_err_letter_line() {
+        use ProblemCreationError::*;
+        assert_eq!(read("1 1 1\na\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 1 1\na 1 1\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 1 1\nab 1\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 1 1\n1 1\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 1 1\na x\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 1 1\na -1\na").err(), Some(InvalidLetterLine));
+        assert_eq!(read("1 2 1\na 1\na 2\na").err(), Some(InvalidLetterLine));
+    }
+
+    #[test]
+    fn create_err_word() {
+        use ProblemCreationError::*;
+        assert_eq!(read("1 1 1\na 1\n").err(), Some(NotEnoughLines));
+        assert_eq!(read("1 1 1\na 1\n\n").err(), Some(InvalidWord));
+        assert_eq!(read("1 1 1\na 1\nb").err(), Some(InvalidWord));
+        assert_eq!(read("1 1 1\na 1\naa").err(), Some(InvalidWord));
+        assert_eq!(read("1 2 1\na 1\nb 1\na b").err(), Some(InvalidWord));
+    }
+
+    #[test]
+    fn create_err_lines() {
+        use ProblemCreationError::*;
+        assert_eq!(read("1 2 1\na 1\n").err(), Some(NotEnoughLines));
+        assert_eq!(read("1 1 2\na 1\na").err(), Some(NotEnoughLines));
+        assert_eq!(read("1 1 1\na 1\na\na").err(), Some(TooManyLines));
+        assert_eq!(read("1 1 1\na 1\na\n\n \n").map(|p| p.initial.len()), Ok(1));
+    }
+}
@@ -0,0 +1,657 @@
+use crate::words::trie::{Cursor, Trie};
+use crate::words::word::{Letter, Word};
+use crate::words::{Dictionary, Value};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::BufRead;
+
+pub const SIZE: usize = 15;
+/// Playing this many tiles in one move earns [`BINGO_BONUS`].
+pub const RACK_SIZE: usize = 7;
+pub const BINGO_BONUS: Value = 50;
+
+const CENTRE: usize = SIZE / 2;
+
+/// Premium squares of the standard board: `W`/`w` triple/double word, `L`/`l` triple/double
+/// letter. The layout is symmetric, so it also applies to the transposed board.
+const PREMIUMS: [&str; SIZE] = [
+    "W..l...W...l..W",
+    ".w...L...L...w.",
+    "..w...l.l...w..",
+    "l..w...l...w..l",
+    "....w.....w....",
+    ".L...L...L...L.",
+    "..l...l.l...l..",
+    "W..l...w...l..W",
+    "..l...l.l...l..",
+    ".L...L...L...L.",
+    "....w.....w....",
+    "l..w...l...w..l",
+    "..w...l.l...w..",
+    ".w...L...L...w.",
+    "W..l...W...l..W",
+];
+
+/// Letter and word multipliers of a square.
+fn multipliers(row: usize, col: usize) -> (Value, Value) {
+    match PREMIUMS[row].as_bytes()[col] {
+        b'W' => (1, 3),
+        b'w' => (1, 2),
+        b'L' => (3, 1),
+        b'l' => (2, 1),
+        _ => (1, 1),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    Across,
+    Down,
+}
+
+type Line = [Option<Letter>; SIZE];
+
+/// Tiles on a `SIZE` by `SIZE` board, `None` for empty squares.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    tiles: [Line; SIZE],
+}
+
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub enum BoardCreationError {
+    InvalidLine,
+    NotEnoughLines,
+    TooManyLines,
+    IOError(String),
+}
+
+impl fmt::Display for BoardCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardCreationError::InvalidLine => {
+                write!(f, "Board lines must have {} letters or dots", SIZE)
+            }
+            BoardCreationError::NotEnoughLines => write!(f, "Not enough board lines"),
+            BoardCreationError::TooManyLines => write!(f, "Too many board lines"),
+            BoardCreationError::IOError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BoardCreationError {}
+
+impl From<std::io::Error> for BoardCreationError {
+    fn from(err: std::io::Error) -> Self {
+        BoardCreationError::IOError(err.to_string())
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            tiles: [[None; SIZE]; SIZE],
+        }
+    }
+}
+
+impl Board {
+    /// Reads `SIZE` lines of `SIZE` squares, `.` for an empty square and a letter for a tile.
+    pub fn try_from_read<R: BufRead>(reader: R) -> Result<Self, BoardCreationError> {
+        use itertools::EitherOrBoth::*;
+        use BoardCreationError::*;
+
+        let mut board = Self::default();
+
+        for zipped in board.tiles.iter_mut().zip_longest(reader.lines()) {
+            let (row, line) = match zipped {
+                Both(row, line) => (row, line?),
+                Left(_) => return Err(NotEnoughLines),
+                Right(_) => return Err(TooManyLines),
+            };
+
+            for zipped in row.iter_mut().zip_longest(line.trim_end().chars()) {
+                match zipped {
+                    Both(square, '.') => *square = None,
+                    Both(square, c) if c.is_alphabetic() => {
+                        *square = c.to_lowercase().next();
+                    }
+                    _ => return Err(InvalidLine),
+                }
+            }
+        }
+
+        Ok(board)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.iter().flatten().all(Option::is_none)
+    }
+
+    /// Puts the tiles of `placement` on the board.
+    pub fn place(&mut self, placement: &Placement) {
+        for (i, &letter) in placement.word.as_slice().iter().enumerate() {
+            let (row, col) = match placement.direction {
+                Direction::Across => (placement.row, placement.col + i),
+                Direction::Down => (placement.row + i, placement.col),
+            };
+            self.tiles[row][col] = Some(letter);
+        }
+    }
+
+    fn transposed(&self) -> Self {
+        let mut transposed = Self::default();
+        for (row, col) in (0..SIZE).cartesian_product(0..SIZE) {
+            transposed.tiles[col][row] = self.tiles[row][col];
+        }
+        transposed
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.tiles {
+            let line: String = row.iter().map(|square| square.unwrap_or('.')).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A legal move: `word` is the whole word formed along `direction`, starting at `row`, `col`,
+/// including tiles that were already on the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+    pub word: Word,
+    /// Number of tiles taken from the rack.
+    pub tiles: usize,
+    pub score: Value,
+}
+
+/// Letters allowed on an empty square by the word crossing it and the score of that word's
+/// existing tiles, `None` if the square has no neighbours across the line.
+type CrossCheck = Option<(Vec<Letter>, Value)>;
+
+/// Generates the moves along one row of a (possibly transposed) board, extending words
+/// through the trie to the right of every anchor square.
+struct LineSearch<'a> {
+    trie: &'a Trie,
+    values: &'a BTreeMap<Letter, (u32, Value)>,
+    direction: Direction,
+    row: usize,
+    line: Line,
+    cross_checks: Vec<CrossCheck>,
+    rack: BTreeMap<Letter, u32>,
+    word: Vec<Letter>,
+    found: Vec<Placement>,
+}
+
+impl LineSearch<'_> {
+    fn value(&self, letter: Letter) -> Value {
+        self.values.get(&letter).map_or(0, |&(_, value)| value)
+    }
+
+    fn allows(&self, col: usize, letter: Letter) -> bool {
+        match &self.cross_checks[col] {
+            Some((allowed, _)) => allowed.binary_search(&letter).is_ok(),
+            None => true,
+        }
+    }
+
+    /// Tries every left part of at most `limit` rack tiles ending just before `anchor`.
+    fn left_part(&mut self, cursor: Cursor, anchor: usize, limit: usize) {
+        self.extend_right(cursor, anchor, anchor, anchor - self.word.len());
+
+        if limit == 0 {
+            return;
+        }
+
+        let trie = self.trie;
+        for (letter, next) in trie.edges(cursor) {
+            if let Some(count) = self.rack.get_mut(&letter).filter(|count| **count > 0) {
+                *count -= 1;
+                self.word.push(letter);
+                self.left_part(next, anchor, limit - 1);
+                self.word.pop();
+                *self.rack.get_mut(&letter).unwrap() += 1;
+            }
+        }
+    }
+
+    fn extend_right(&mut self, cursor: Cursor, col: usize, anchor: usize, start: usize) {
+        let square = self.line.get(col).copied().flatten();
+
+        if square.is_none() && col > anchor && self.word.len() >= 2 && self.trie.is_word(cursor) {
+            self.record(start);
+        }
+
+        if col == SIZE {
+            return;
+        }
+
+        let trie = self.trie;
+        match square {
+            Some(letter) => {
+                if let Some(next) = trie.step(cursor, letter) {
+                    self.word.push(letter);
+                    self.extend_right(next, col + 1, anchor, start);
+                    self.word.pop();
+                }
+            }
+            None => {
+                for (letter, next) in trie.edges(cursor) {
+                    if !self.allows(col, letter) {
+                        continue;
+                    }
+                    if let Some(count) = self.rack.get_mut(&letter).filter(|count| **count > 0) {
+                        *count -= 1;
+                        self.word.push(letter);
+                        self.extend_right(next, col + 1, anchor, start);
+                        self.word.pop();
+                        *self.rack.get_mut(&letter).unwrap() += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, start: usize) {
+        let (mut main, mut word_multiplier, mut crossing, mut tiles) = (0, 1, 0, 0);
+
+        for (i, &letter) in self.word.iter().enumerate() {
+            let col = start + i;
+            let value = self.value(letter);
+
+            if self.line[col].is_some() {
+                main += value;
+                continue;
+            }
+
+            let (letter_multiplier, multiplier) = multipliers(self.row, col);
+            main += value * letter_multiplier;
+            word_multiplier *= multiplier;
+            tiles += 1;
+
+            if let Some((_, score)) = &self.cross_checks[col] {
+                crossing += (score + value * letter_multiplier) * multiplier;
+            }
+        }
+
+        let bingo = if tiles == RACK_SIZE { BINGO_BONUS } else { 0 };
+        let (row, col) = match self.direction {
+            Direction::Across => (self.row, start),
+            Direction::Down => (start, self.row),
+        };
+
+        self.found.push(Placement {
+            row,
+            col,
+            direction: self.direction,
+            word: Word::new(self.word.clone().into_boxed_slice()),
+            tiles,
+            score: main * word_multiplier + crossing + bingo,
+        });
+    }
+}
+
+/// Cross-checks of every square of `row`, from the words they would form along the columns.
+fn cross_checks(board: &Board, row: usize, dictionary: &Dictionary) -> Vec<CrossCheck> {
+    let trie = &dictionary.acceptable_words;
+    let value = |letter| {
+        dictionary
+            .available_letters
+            .get(&letter)
+            .map_or(0, |&(_, value)| value)
+    };
+
+    (0..SIZE)
+        .map(|col| {
+            if board.tiles[row][col].is_some() {
+                return None;
+            }
+
+            let column = |rows: &mut dyn Iterator<Item = usize>| -> Vec<Letter> {
+                rows.map_while(|r| board.tiles[r][col]).collect()
+            };
+            let mut above = column(&mut (0..row).rev());
+            above.reverse();
+            let below = column(&mut (row + 1..SIZE));
+
+            if above.is_empty() && below.is_empty() {
+                return None;
+            }
+
+            let allowed = above
+                .iter()
+                .try_fold(trie.root(), |cursor, &letter| trie.step(cursor, letter))
+                .into_iter()
+                .flat_map(|cursor| trie.edges(cursor))
+                .filter(|&(_, cursor)| {
+                    let end = below
+                        .iter()
+                        .try_fold(cursor, |cursor, &letter| trie.step(cursor, letter));
+                    matches!(end, Some(cursor) if trie.is_word(cursor))
+                })
+                .map(|(letter, _)| letter)
+                .collect();
+            let score = above
+                .iter()
+                .chain(&below)
+                .map(|&letter| value(letter))
+                .sum();
+
+            Some((allowed, score))
+        })
+        .collect()
+}
+
+/// Moves along the rows of `board`, reported as `direction` moves.
+fn line_placements(board: &Board, direction: Direction, dictionary: &Dictionary) -> Vec<Placement> {
+    let mut rack: BTreeMap<_, _> = dictionary
+        .available_letters
+        .iter()
+        .map(|(&letter, &(count, _))| (letter, count))
+        .collect();
+    let empty = board.is_empty();
+    let mut found = Vec::new();
+
+    for row in 0..SIZE {
+        let line = board.tiles[row];
+        let is_anchor = |col: usize| {
+            let neighbours = [
+                row.checked_sub(1).map(|r| (r, col)),
+                Some((row + 1, col)),
+                col.checked_sub(1).map(|c| (row, c)),
+                Some((row, col + 1)),
+            ];
+
+            line[col].is_none()
+                && if empty {
+                    (row, col) == (CENTRE, CENTRE)
+                } else {
+                    neighbours
+                        .iter()
+                        .flatten()
+                        .any(|&(r, c)| r < SIZE && c < SIZE && board.tiles[r][c].is_some())
+                }
+        };
+        let anchors: Vec<_> = (0..SIZE).filter(|&col| is_anchor(col)).collect();
+        if anchors.is_empty() {
+            continue;
+        }
+
+        let mut search = LineSearch {
+            trie: &dictionary.acceptable_words,
+            values: &dictionary.available_letters,
+            direction,
+            row,
+            line,
+            cross_checks: cross_checks(board, row, dictionary),
+            rack,
+            word: Vec::new(),
+            found,
+        };
+
+        for &anchor in &anchors {
+            let trie = search.trie;
+            let tiles_before = (0..anchor).rev().take_while(|&col| line[col].is_some());
+            let start = tiles_before.last();
+
+            match start {
+                Some(start) => {
+                    search.word.extend(line[start..anchor].iter().flatten());
+                    let cursor = search
+                        .word
+                        .iter()
+                        .try_fold(trie.root(), |cursor, &letter| trie.step(cursor, letter));
+                    if let Some(cursor) = cursor {
+                        search.extend_right(cursor, anchor, anchor, start);
+                    }
+                    search.word.clear();
+                }
+                None => {
+                    let limit = (0..anchor)
+                        .rev()
+                        .take_while(|&col| line[col].is_none() && !anchors.contains(&col))
+                        .count();
+                    search.left_part(trie.root(), anchor, limit);
+                }
+            }
+        }
+
+        rack = search.rack;
+        found = search.found;
+    }
+
+    found
+}
+
+/// Every legal move on `board` using the tiles of `dictionary.available_letters`, scored with
+/// their values. Letters on the board missing from the table score 0, blanks are not supported.
+pub fn placements(board: &Board, dictionary: &Dictionary) -> Vec<Placement> {
+    let mut placements = line_placements(board, Direction::Across, dictionary);
+    placements.extend(line_placements(
+        &board.transposed(),
+        Direction::Down,
+        dictionary,
+    ));
+    placements
+}
+
+/// The highest scoring legal move, preferring the first in reading order among equal ones.
+pub fn best_placement(board: &Board, dictionary: &Dictionary) -> Option<Placement> {
+    placements(board, dictionary)
+        .into_iter()
+        .min_by_key(|p| (std::cmp::Reverse(p.score), p.row, p.col, p.direction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::tests::dictionary;
+    use crate::words::words;
+    use std::io::Cursor as Reader;
+
+    fn board(tiles: &[(usize, usize, &str, Direction)]) -> Board {
+        let mut board = Board::default();
+        for &(row, col, word, direction) in tiles {
+            board.place(&Placement {
+                row,
+                col,
+                direction,
+                word: Word::from(word),
+                tiles: 0,
+                score: 0,
+            });
+        }
+        board
+    }
+
+    fn letters(letters: &[(Letter, u32, Value)]) -> BTreeMap<Letter, (u32, Value)> {
+        letters.iter().map(|&(c, n, v)| (c, (n, v))).collect()
+    }
+
+    /// Squares of the words of at least two letters along the rows and columns.
+    fn board_words(board: &Board) -> Vec<Vec<(usize, usize)>> {
+        let rows = (0..SIZE).map(|r| (0..SIZE).map(|c| (r, c)).collect_vec());
+        let cols = (0..SIZE).map(|c| (0..SIZE).map(|r| (r, c)).collect_vec());
+
+        rows.chain(cols)
+            .flat_map(|line| {
+                line.into_iter()
+                    .group_by(|&(r, c)| board.tiles[r][c].is_some())
+                    .into_iter()
+                    .filter(|(tile, _)| *tile)
+                    .map(|(_, squares)| squares.collect_vec())
+                    .filter(|squares| squares.len() >= 2)
+                    .collect_vec()
+            })
+            .collect()
+    }
+
+    /// Checks `placement` against the rules directly on the boards before and after it.
+    fn assert_legal(before: &Board, placement: &Placement, dictionary: &Dictionary) {
+        let mut after = before.clone();
+        after.place(placement);
+        let new = |(r, c): (usize, usize)| before.tiles[r][c].is_none();
+        let value = |(r, c): (usize, usize)| {
+            let letter = after.tiles[r][c].unwrap();
+            dictionary.available_letters.get(&letter).map_or(0, |l| l.1)
+        };
+
+        let mut used = BTreeMap::new();
+        let squares = (0..SIZE).cartesian_product(0..SIZE);
+        for square in squares
+            .clone()
+            .filter(|&(r, c)| after.tiles[r][c].is_some())
+        {
+            if new(square) {
+                *used
+                    .entry(after.tiles[square.0][square.1].unwrap())
+                    .or_insert(0) += 1;
+            } else {
+                assert_eq!(
+                    before.tiles[square.0][square.1],
+                    after.tiles[square.0][square.1]
+                );
+            }
+        }
+        assert_eq!(used.values().sum::<u32>() as usize, placement.tiles);
+        for (letter, count) in used {
+            assert!(count <= dictionary.available_letters[&letter].0);
+        }
+
+        let touches = |(r, c): (usize, usize)| {
+            let neighbours = [
+                (r.wrapping_sub(1), c),
+                (r + 1, c),
+                (r, c.wrapping_sub(1)),
+                (r, c + 1),
+            ];
+            neighbours
+                .iter()
+                .any(|&(r, c)| r < SIZE && c < SIZE && before.tiles[r][c].is_some())
+        };
+        let placed: Vec<_> = squares
+            .filter(|&s| after.tiles[s.0][s.1].is_some() && new(s))
+            .collect();
+        if before.is_empty() {
+            assert!(placed.contains(&(CENTRE, CENTRE)));
+        } else {
+            assert!(placed.iter().any(|&s| touches(s)));
+        }
+
+        let mut score = 0;
+        for word in board_words(&after) {
+            let letters: Vec<_> = word
+                .iter()
+                .map(|&(r, c)| after.tiles[r][c].unwrap())
+                .collect();
+            assert!(
+                dictionary.acceptable_words.contains(&letters),
+                "{:?}",
+                letters
+            );
+
+            if word.iter().any(|&s| new(s)) {
+                let (sum, multiplier) = word.iter().fold((0, 1), |(sum, multiplier), &s| {
+                    let (l, w) = if new(s) {
+                        multipliers(s.0, s.1)
+                    } else {
+                        (1, 1)
+                    };
+                    (sum + value(s) * l, multiplier * w)
+                });
+                score += sum * multiplier;
+            }
+        }
+        if placement.tiles == RACK_SIZE {
+            score += BINGO_BONUS;
+        }
+        assert_eq!(placement.score, score, "{:?}", placement);
+    }
+
+    #[test]
+    fn read_board() {
+        let mut text = ".".repeat(SIZE) + "\n";
+        text = text.repeat(SIZE - 1) + "..Żółw.........\n";
+        let board = Board::try_from_read(Reader::new(&text)).unwrap();
+        assert_eq!(
+            board.tiles[SIZE - 1][2..6],
+            [Some('ż'), Some('ó'), Some('ł'), Some('w')]
+        );
+        assert_eq!(board.to_string(), text.to_lowercase());
+
+        let read = |text: String| Board::try_from_read(Reader::new(text)).err();
+        use BoardCreationError::*;
+        assert_eq!(read(text.repeat(2)), Some(TooManyLines));
+        assert_eq!(read(text.lines().skip(1).join("\n")), Some(NotEnoughLines));
+        assert_eq!(read(text.replace("..Żółw", ".Żółw")), Some(InvalidLine));
+        assert_eq!(read(text.replace("..Żółw", "..Żó2w")), Some(InvalidLine));
+    }
+
+    #[test]
+    fn first_move_bingo() {
+        let available_letters = letters(&[
+            ('a', 2, 1),
+            ('b', 2, 3),
+            ('c', 1, 3),
+            ('e', 1, 1),
+            ('g', 1, 2),
+        ]);
+        let dictionary = Dictionary::new(words("cabbage\ncab\nbag"), available_letters);
+
+        let best = best_placement(&Board::default(), &dictionary).unwrap();
+        // `b` on the double letter square and the whole word doubled by the centre, the
+        // transposed move across row 7 scores the same but comes later in reading order
+        assert_eq!(best.word, Word::from("cabbage"));
+        assert_eq!(
+            (best.row, best.col, best.direction),
+            (1, CENTRE, Direction::Down)
+        );
+        assert_eq!((best.tiles, best.score), (7, (14 + 3) * 2 + BINGO_BONUS));
+    }
+
+    #[test]
+    fn extends_words_on_the_board() {
+        let available_letters = letters(&[('a', 0, 1), ('c', 0, 3), ('s', 1, 1), ('t', 0, 1)]);
+        let dictionary = Dictionary::new(words("cat\ncats\nas\nat"), available_letters);
+        let board = board(&[(CENTRE, 6, "cat", Direction::Across)]);
+
+        let mut found = placements(&board, &dictionary);
+        found.sort_by_key(|p| (p.score, p.row, p.col));
+        let found: Vec<_> = found
+            .iter()
+            .map(|p| (p.row, p.col, p.direction, p.word.to_string(), p.score))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (CENTRE, 7, Direction::Down, "as".to_string(), 2),
+                (CENTRE, 6, Direction::Across, "cats".to_string(), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn placements_are_legal() {
+        let dictionary = dictionary();
+        let mut board = Board::default();
+
+        for _ in 0..3 {
+            let found = placements(&board, &dictionary);
+            assert!(!found.is_empty());
+            for placement in &found {
+                assert_legal(&board, placement, &dictionary);
+            }
+
+            let best = best_placement(&board, &dictionary).unwrap();
+            assert_eq!(Some(best.score), found.iter().map(|p| p.score).max());
+            board.place(&best);
+        }
+    }
+}
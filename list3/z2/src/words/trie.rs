@@ -0,0 +1,275 @@
+use crate::words::word::Letter;
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
+
+type NodeIndex = u32;
+
+const ROOT: NodeIndex = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    terminal: bool,
+    /// Sorted by letter.
+    children: Vec<(Letter, NodeIndex)>,
+}
+
+impl Node {
+    fn child(&self, letter: Letter) -> Option<NodeIndex> {
+        self.children
+            .binary_search_by_key(&letter, |&(l, _)| l)
+            .ok()
+            .map(|i| self.children[i].1)
+    }
+}
+
+/// Node reached by spelling some prefix, for walking the trie one letter at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor(NodeIndex);
+
+/// Prefix tree of the acceptable words, answering both "is this a word" and
+/// "can this be extended to a word".
+#[derive(Debug, Clone)]
+pub struct Trie {
+    nodes: Vec<Node>,
+    len: usize,
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+            len: 0,
+        }
+    }
+
+    /// Number of distinct words.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, word: &[Letter]) {
+        let mut node = ROOT;
+
+        for &letter in word {
+            let children = &self.nodes[node as usize].children;
+            node = match children.binary_search_by_key(&letter, |&(l, _)| l) {
+                Ok(i) => children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len() as NodeIndex;
+                    self.nodes[node as usize]
+                        .children
+                        .insert(i, (letter, child));
+                    self.nodes.push(Node::default());
+                    child
+                }
+            };
+        }
+
+        let node = &mut self.nodes[node as usize];
+        if !node.terminal {
+            node.terminal = true;
+            self.len += 1;
+        }
+    }
+
+    /// Nodes along the path spelled by `word`, starting with the root, ending early
+    /// when no word continues that way.
+    fn path<'a>(&'a self, word: &'a [Letter]) -> impl Iterator<Item = &'a Node> + 'a {
+        let root = &self.nodes[ROOT as usize];
+        std::iter::once(root).chain(word.iter().scan(root, move |node, &letter| {
+            *node = &self.nodes[node.child(letter)? as usize];
+            Some(*node)
+        }))
+    }
+
+    fn find(&self, prefix: &[Letter]) -> Option<&Node> {
+        prefix
+            .iter()
+            .try_fold(&self.nodes[ROOT as usize], |node, &letter| {
+                Some(&self.nodes[node.child(letter)? as usize])
+            })
+    }
+
+    pub fn contains(&self, word: &[Letter]) -> bool {
+        matches!(self.find(word), Some(node) if node.terminal)
+    }
+
+    /// Length of the longest prefix of `word` that some word starts with.
+    pub fn valid_prefix_len(&self, word: &[Letter]) -> usize {
+        self.path(word).count() - 1
+    }
+
+    /// Lengths of the prefixes of `word` that are words themselves, in increasing order.
+    pub fn word_prefix_lens<'a>(&'a self, word: &'a [Letter]) -> impl Iterator<Item = usize> + 'a {
+        self.path(word)
+            .enumerate()
+            .filter(|(_, node)| node.terminal)
+            .map(|(len, _)| len)
+    }
+
+    /// Letters that can follow `prefix` in some word.
+    pub fn next_letters(&self, prefix: &[Letter]) -> impl Iterator<Item = Letter> + '_ {
+        self.find(prefix)
+            .into_iter()
+            .flat_map(|node| node.children.iter().map(|&(letter, _)| letter))
+    }
+
+    pub fn root(&self) -> Cursor {
+        Cursor(ROOT)
+    }
+
+    pub fn step(&self, cursor: Cursor, letter: Letter) -> Option<Cursor> {
+        self.nodes[cursor.0 as usize].child(letter).map(Cursor)
+    }
+
+    /// Whether the prefix leading to `cursor` is a word.
+    pub fn is_word(&self, cursor: Cursor) -> bool {
+        self.nodes[cursor.0 as usize].terminal
+    }
+
+    /// Letters that can follow the prefix leading to `cursor`, with the cursors they lead to.
+    pub fn edges(&self, cursor: Cursor) -> impl Iterator<Item = (Letter, Cursor)> + '_ {
+        self.nodes[cursor.0 as usize]
+            .children
+            .iter()
+            .map(|&(letter, child)| (letter, Cursor(child)))
+    }
+
+    /// Every letter used by some word.
+    pub fn alphabet(&self) -> BTreeSet<Letter> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.children.iter().map(|&(letter, _)| letter))
+            .collect()
+    }
+
+    /// All words in lexicographic order.
+    pub fn iter(&self) -> Words<'_> {
+        Words {
+            trie: self,
+            stack: vec![(ROOT, None)],
+            word: Vec::new(),
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a [Letter]> for Trie {
+    fn from_iter<T: IntoIterator<Item = &'a [Letter]>>(iter: T) -> Self {
+        let mut trie = Self::new();
+        for word in iter {
+            trie.insert(word);
+        }
+        trie
+    }
+}
+
+/// Depth-first iterator over the words of a [`Trie`].
+#[derive(Debug, Clone)]
+pub struct Words<'a> {
+    trie: &'a Trie,
+    /// Nodes still to visit with the letter leading to each of them, `None` marks the root.
+    stack: Vec<(NodeIndex, Option<(usize, Letter)>)>,
+    word: Vec<Letter>,
+}
+
+impl Iterator for Words<'_> {
+    type Item = Vec<Letter>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, edge)) = self.stack.pop() {
+            let node = &self.trie.nodes[index as usize];
+            if let Some((depth, letter)) = edge {
+                self.word.truncate(depth);
+                self.word.push(letter);
+            }
+
+            let depth = self.word.len();
+            self.stack.extend(
+                node.children
+                    .iter()
+                    .rev()
+                    .map(|&(letter, child)| (child, Some((depth, letter)))),
+            );
+
+            if node.terminal {
+                return Some(self.word.clone());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(word: &str) -> Vec<Letter> {
+        word.chars().collect()
+    }
+
+    fn example() -> Trie {
+        let words: Vec<_> = ["bean", "be", "bead", "stead", "bean", "a", "żółw"]
+            .iter()
+            .map(|w| letters(w))
+            .collect();
+        words.iter().map(Vec::as_slice).collect()
+    }
+
+    #[test]
+    fn contains() {
+        let trie = example();
+        assert_eq!(trie.len(), 6);
+        assert!(trie.contains(&letters("bean")));
+        assert!(trie.contains(&letters("be")));
+        assert!(trie.contains(&letters("żółw")));
+        assert!(!trie.contains(&letters("bea")));
+        assert!(!trie.contains(&letters("beans")));
+        assert!(!trie.contains(&letters("")));
+    }
+
+    #[test]
+    fn prefixes() {
+        let trie = example();
+        assert_eq!(trie.valid_prefix_len(&letters("beast")), 3);
+        assert_eq!(trie.valid_prefix_len(&letters("xyz")), 0);
+        assert_eq!(trie.valid_prefix_len(&letters("bead")), 4);
+        assert_eq!(trie.valid_prefix_len(&letters("żółty")), 3);
+
+        let beans = letters("beans");
+        assert_eq!(
+            trie.word_prefix_lens(&beans).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        let ab = letters("ab");
+        assert_eq!(trie.word_prefix_lens(&ab).collect::<Vec<_>>(), vec![1]);
+
+        assert_eq!(
+            trie.next_letters(&letters("bea")).collect::<Vec<_>>(),
+            letters("dn")
+        );
+        assert_eq!(trie.next_letters(&letters("x")).count(), 0);
+    }
+
+    #[test]
+    fn alphabet() {
+        let alphabet: String = example().alphabet().into_iter().collect();
+        assert_eq!(alphabet, "abdenstwółż");
+    }
+
+    #[test]
+    fn iter_is_sorted() {
+        let words: Vec<String> = example().iter().map(|w| w.into_iter().collect()).collect();
+        assert_eq!(words, vec!["a", "be", "bead", "bean", "stead", "żółw"]);
+    }
+}
@@ -1,76 +1,104 @@
 use rand::prelude::*;
+use std::fmt::{self, Write as _};
 
+/// A single letter of the alphabet, which may be any Unicode scalar value.
+pub type Letter = char;
+
+/// Sequence of letters, not necessarily a word from the dictionary.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Word {
-    bytes: Box<[u8]>,
+    letters: Box<[Letter]>,
 }
 
 impl Word {
-    pub fn as_slice(&self) -> &[u8] {
-        &self.bytes
+    pub fn as_slice(&self) -> &[Letter] {
+        &self.letters
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.letters
+            .iter()
+            .try_for_each(|&letter| f.write_char(letter))
     }
 }
 
 impl Word {
-    pub fn new(bytes: Box<[u8]>) -> Self {
-        Self { bytes }
+    pub fn new(letters: Box<[Letter]>) -> Self {
+        Self { letters }
     }
+}
 
-    pub fn from_string(word: String) -> Self {
-        Self::new(word.into_bytes().into_boxed_slice())
+impl From<&str> for Word {
+    fn from(word: &str) -> Self {
+        Self::new(word.chars().collect())
     }
 }
 
 impl Word {
     pub fn recombine(s1: &Self, s2: &Self, i: usize, j: usize) -> Self {
         let len = i + s2.as_slice().len() - j;
-        let mut bytes = Vec::with_capacity(len);
-        bytes.extend_from_slice(&s1.as_slice()[..i]);
-        bytes.extend_from_slice(&s2.as_slice()[j..]);
+        let mut letters = Vec::with_capacity(len);
+        letters.extend_from_slice(&s1.as_slice()[..i]);
+        letters.extend_from_slice(&s2.as_slice()[j..]);
 
         Self {
-            bytes: bytes.into_boxed_slice(),
+            letters: letters.into_boxed_slice(),
         }
     }
 
-    pub fn mutate_lengthen(specimen: &Self, mut letters: Vec<u8>) -> Self {
+    pub fn mutate_lengthen(specimen: &Self, mut letters: Vec<Letter>) -> Self {
         let specimen = specimen.as_slice();
         let count = letters.len();
         letters.reserve_exact(specimen.len());
-        letters.extend(std::iter::repeat(0).take(specimen.len()));
+        letters.resize(count + specimen.len(), Letter::default());
         letters.copy_within(..count, specimen.len());
         letters[..specimen.len()].copy_from_slice(specimen);
 
         Self {
-            bytes: letters.into_boxed_slice(),
+            letters: letters.into_boxed_slice(),
         }
     }
 
     pub fn mutate_shorten(specimen: &Self, pos: usize, len: usize) -> Self {
-        let mut bytes = Vec::with_capacity(specimen.as_slice().len() - len);
-        bytes.extend_from_slice(&specimen.as_slice()[..pos]);
-        bytes.extend_from_slice(&specimen.as_slice()[pos + len..]);
+        let mut letters = Vec::with_capacity(specimen.as_slice().len() - len);
+        letters.extend_from_slice(&specimen.as_slice()[..pos]);
+        letters.extend_from_slice(&specimen.as_slice()[pos + len..]);
 
         Self {
-            bytes: bytes.into_boxed_slice(),
+            letters: letters.into_boxed_slice(),
         }
     }
 
     pub fn mutate_shuffle(specimen: &Self, rng: &mut impl Rng) -> Self {
-        let mut bytes = specimen.bytes.clone();
+        let mut letters = specimen.letters.clone();
 
-        bytes.shuffle(rng);
+        letters.shuffle(rng);
 
-        Self { bytes }
+        Self { letters }
     }
 
-    pub fn mutate_replace_letters(specimen: &Self, positions: &[(usize, u8)]) -> Self {
-        let mut bytes = specimen.bytes.clone();
+    pub fn mutate_replace_letters(specimen: &Self, positions: &[(usize, Letter)]) -> Self {
+        let mut letters = specimen.letters.clone();
 
         for &(pos, c) in positions {
-            bytes[pos] = c;
+            letters[pos] = c;
         }
 
-        Self { bytes }
+        Self { letters }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let word = Word::from("żółw\"\n");
+        assert_eq!(word.to_string(), "żółw\"\n");
+        assert_eq!(format!("{} {}", word, word), "żółw\"\n żółw\"\n");
+        assert_eq!(Word::default().to_string(), "");
     }
 }
@@ -0,0 +1,311 @@
+use crate::words::anagram::AnagramIndex;
+use crate::words::word::{Letter, Word};
+use crate::words::Value;
+use rand::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+const GENERATION_SIZE: usize = 500;
+const FILL_ATTEMPTS: usize = 32;
+
+/// Words built from disjoint parts of the rack, worth the sum of their values.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WordSet {
+    pub words: Vec<Word>,
+    pub value: Value,
+}
+
+#[derive(Debug)]
+struct Candidate<'a> {
+    /// Letter indices into the rack and how many of each the word uses.
+    counts: Vec<(usize, u32)>,
+    value: Value,
+    word: &'a Word,
+}
+
+/// Words fitting the rack on their own, one per multiset of letters, most valuable first.
+#[derive(Debug)]
+struct Candidates<'a> {
+    rack: Vec<u32>,
+    letter_values: Vec<Value>,
+    candidates: Vec<Candidate<'a>>,
+}
+
+impl<'a> Candidates<'a> {
+    fn new(index: &'a AnagramIndex, available_letters: &'a BTreeMap<Letter, (u32, Value)>) -> Self {
+        let letters: Vec<_> = available_letters.keys().copied().collect();
+        let position = |letter| letters.binary_search(&letter).unwrap();
+
+        let mut candidates: Vec<_> = index
+            .fitting(available_letters)
+            .map(|(signature, words, value)| {
+                let mut counts: Vec<(usize, u32)> = Vec::new();
+                for &letter in signature {
+                    match counts.last_mut() {
+                        Some((i, count)) if letters[*i] == letter => *count += 1,
+                        _ => counts.push((position(letter), 1)),
+                    }
+                }
+                Candidate {
+                    counts,
+                    value,
+                    word: &words[0],
+                }
+            })
+            .collect();
+        candidates.sort_by(|c1, c2| c2.value.cmp(&c1.value).then_with(|| c1.word.cmp(c2.word)));
+
+        Self {
+            rack: available_letters
+                .values()
+                .map(|&(count, _)| count)
+                .collect(),
+            letter_values: available_letters
+                .values()
+                .map(|&(_, value)| value)
+                .collect(),
+            candidates,
+        }
+    }
+
+    fn fits(&self, candidate: usize, remaining: &[u32]) -> bool {
+        self.candidates[candidate]
+            .counts
+            .iter()
+            .all(|&(letter, count)| remaining[letter] >= count)
+    }
+
+    fn take(&self, candidate: usize, remaining: &mut [u32]) {
+        for &(letter, count) in &self.candidates[candidate].counts {
+            remaining[letter] -= count;
+        }
+    }
+
+    fn put_back(&self, candidate: usize, remaining: &mut [u32]) {
+        for &(letter, count) in &self.candidates[candidate].counts {
+            remaining[letter] += count;
+        }
+    }
+
+    fn value(&self, chosen: &[usize]) -> Value {
+        chosen.iter().map(|&c| self.candidates[c].value).sum()
+    }
+
+    fn to_set(&self, chosen: &[usize]) -> WordSet {
+        WordSet {
+            words: chosen
+                .iter()
+                .map(|&c| self.candidates[c].word.clone())
+                .collect(),
+            value: self.value(chosen),
+        }
+    }
+
+    /// Depth-first search over the choices of candidates `from..` on, pruned by the value of
+    /// the letters left on the rack.
+    fn branch(
+        &self,
+        from: usize,
+        remaining: &mut [u32],
+        chosen: &mut Vec<usize>,
+        value: Value,
+        best: &mut (Value, Vec<usize>),
+    ) {
+        if value > best.0 {
+            *best = (value, chosen.clone());
+        }
+
+        let bound: Value = remaining
+            .iter()
+            .zip(&self.letter_values)
+            .map(|(&count, &value)| Value::from(count) * value)
+            .sum();
+        if value + bound <= best.0 {
+            return;
+        }
+
+        for candidate in from..self.candidates.len() {
+            if self.fits(candidate, remaining) {
+                self.take(candidate, remaining);
+                chosen.push(candidate);
+                let next = value + self.candidates[candidate].value;
+                self.branch(candidate, remaining, chosen, next, best);
+                chosen.pop();
+                self.put_back(candidate, remaining);
+            }
+        }
+    }
+
+    /// Adds random fitting candidates to `chosen` until a few attempts in a row fail.
+    fn fill(&self, chosen: &mut Vec<usize>, remaining: &mut [u32], rng: &mut impl Rng) {
+        let mut failures = 0;
+        while failures < FILL_ATTEMPTS {
+            let candidate = rng.gen_range(0, self.candidates.len());
+            if self.fits(candidate, remaining) {
+                self.take(candidate, remaining);
+                chosen.push(candidate);
+                failures = 0;
+            } else {
+                failures += 1;
+            }
+        }
+        chosen.sort_unstable();
+    }
+
+    /// The words of `words` that fit the rack together, taken in random order, topped up
+    /// with random candidates.
+    fn repair(&self, mut words: Vec<usize>, rng: &mut impl Rng) -> Vec<usize> {
+        let mut remaining = self.rack.clone();
+        let mut chosen = Vec::with_capacity(words.len());
+
+        words.shuffle(rng);
+        for candidate in words {
+            if self.fits(candidate, &remaining) {
+                self.take(candidate, &mut remaining);
+                chosen.push(candidate);
+            }
+        }
+
+        self.fill(&mut chosen, &mut remaining, rng);
+        chosen
+    }
+}
+
+/// The most valuable set of words, found exactly by branch and bound. The search is
+/// exponential in the number of tiles, so it is meant for small racks.
+pub fn best_set(
+    index: &AnagramIndex,
+    available_letters: &BTreeMap<Letter, (u32, Value)>,
+) -> WordSet {
+    let candidates = Candidates::new(index, available_letters);
+    let mut remaining = candidates.rack.clone();
+    let mut best = (0, Vec::new());
+
+    candidates.branch(0, &mut remaining, &mut Vec::new(), 0, &mut best);
+
+    candidates.to_set(&best.1)
+}
+
+/// Genetic search over sets of words, each specimen always fitting the rack.
+pub fn search(
+    index: &AnagramIndex,
+    available_letters: &BTreeMap<Letter, (u32, Value)>,
+    time_limit: Instant,
+) -> WordSet {
+    let candidates = Candidates::new(index, available_letters);
+    if candidates.candidates.is_empty() {
+        return WordSet::default();
+    }
+
+    let rng = &mut thread_rng();
+    let mut population: Vec<Vec<usize>> = (0..GENERATION_SIZE)
+        .map(|_| candidates.repair(Vec::new(), rng))
+        .collect();
+
+    while Instant::now() < time_limit {
+        population.sort_unstable_by_key(|s| std::cmp::Reverse(candidates.value(s)));
+        population.dedup();
+        population.truncate(GENERATION_SIZE);
+
+        let weights: Vec<_> = population.iter().map(|s| candidates.value(s) + 1).collect();
+        let parents = rand::distributions::WeightedIndex::new(&weights).unwrap();
+
+        for _ in 0..GENERATION_SIZE {
+            let s1 = &population[rng.sample(&parents)];
+            let s2 = &population[rng.sample(&parents)];
+
+            let mut words: Vec<_> = s1.iter().filter(|_| rng.gen_bool(0.5)).copied().collect();
+            words.extend(s2.iter().filter(|_| rng.gen_bool(0.5)));
+            if !words.is_empty() && rng.gen_bool(0.2) {
+                words.swap_remove(rng.gen_range(0, words.len()));
+            }
+
+            let child = candidates.repair(words, rng);
+            population.push(child);
+        }
+    }
+
+    let best = population
+        .iter()
+        .max_by_key(|s| candidates.value(s))
+        .unwrap();
+    candidates.to_set(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::words::tests::{available_letters, dictionary};
+    use crate::words::Dictionary;
+    use std::time::Duration;
+
+    fn letters_fit(set: &WordSet, available_letters: &BTreeMap<Letter, (u32, Value)>) -> bool {
+        let mut used = BTreeMap::new();
+        for &letter in set.words.iter().flat_map(Word::as_slice) {
+            *used.entry(letter).or_insert(0) += 1;
+        }
+        used.iter().all(
+            |(letter, &count)| matches!(available_letters.get(letter), Some(&(n, _)) if count <= n),
+        )
+    }
+
+    /// Best value over every sequence of words, trying all of them at each step.
+    fn brute_force(words: &[Word], dictionary: &Dictionary, set: &mut Vec<Word>) -> Value {
+        let fitting = WordSet {
+            words: set.clone(),
+            value: 0,
+        };
+        if !letters_fit(&fitting, &dictionary.available_letters) {
+            return 0;
+        }
+
+        let value = set.iter().filter_map(|w| dictionary.word_value(w)).sum();
+        words.iter().fold(value, |best, word| {
+            set.push(word.clone());
+            let value = brute_force(words, dictionary, set);
+            set.pop();
+            best.max(value)
+        })
+    }
+
+    #[test]
+    fn best_set_matches_brute_force() {
+        let words = [
+            "bean", "abed", "bead", "stead", "daubs", "aaa", "at", "pa", "x",
+        ];
+        let dictionary = Dictionary::new(words.iter().copied(), available_letters());
+        let index = AnagramIndex::new(&dictionary.acceptable_words);
+        let words: Vec<_> = words.iter().map(|&w| Word::from(w)).collect();
+
+        let set = best_set(&index, &dictionary.available_letters);
+
+        assert!(letters_fit(&set, &dictionary.available_letters));
+        assert_eq!(
+            set.value,
+            set.words
+                .iter()
+                .map(|w| dictionary.word_value(w).unwrap())
+                .sum::<Value>()
+        );
+        assert_eq!(set.value, brute_force(&words, &dictionary, &mut Vec::new()));
+        assert_eq!(set.value, 19);
+    }
+
+    #[test]
+    fn search_returns_valid_set() {
+        let dictionary = dictionary();
+        let index = AnagramIndex::new(&dictionary.acceptable_words);
+        let optimum = best_set(&index, &dictionary.available_letters);
+
+        let set = search(
+            &index,
+            &dictionary.available_letters,
+            Instant::now() + Duration::from_millis(500),
+        );
+
+        assert!(letters_fit(&set, &dictionary.available_letters));
+        assert!(set.words.iter().all(|w| dictionary.word_value(w).is_some()));
+        assert!(set.value <= optimum.value);
+        assert!(set.value >= index.top_words(&dictionary.available_letters, 1)[0].value);
+    }
+}